@@ -0,0 +1,264 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::Date;
+use tracing::debug;
+
+use crate::{
+    mdl::full_date,
+    schema::{AAMVA_NAMESPACE, parse_date},
+};
+
+/// The elements of the AAMVA `org.iso.18013.5.1.aamva` namespace, typed.
+///
+/// Elements that were not returned, or whose value does not match the AAMVA
+/// encoding, are left empty.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AamvaExtensions {
+    pub dhs_compliance: Option<DhsCompliance>,
+    pub dhs_compliance_text: Option<String>,
+    pub dhs_temporary_lawful_status: Option<bool>,
+    /// ISO/IEC 5218: 0 not known, 1 male, 2 female, 9 not applicable.
+    pub sex: Option<u8>,
+    /// e.g. `JR`, `SR` or `III`.
+    pub name_suffix: Option<String>,
+    pub family_name_truncation: Option<Truncation>,
+    pub given_name_truncation: Option<Truncation>,
+    pub aka_family_name: Option<String>,
+    pub aka_given_name: Option<String>,
+    pub aka_suffix: Option<String>,
+    pub organ_donor: Option<bool>,
+    pub veteran: Option<bool>,
+    pub cdl_indicator: Option<bool>,
+    pub edl_credential: Option<EdlCredential>,
+    /// AAMVA weight range code, 0 (up to 31 kg) to 9 (over 146 kg).
+    pub weight_range: Option<u8>,
+    pub race_ethnicity: Option<RaceEthnicity>,
+    /// FIPS county code.
+    pub resident_county: Option<String>,
+    #[serde(default, with = "full_date")]
+    pub hazmat_endorsement_expiration_date: Option<Date>,
+    #[serde(default)]
+    pub domestic_driving_privileges: Vec<DomesticDrivingPrivilege>,
+    pub aamva_version: Option<u32>,
+}
+
+/// REAL ID compliance, `DHS_compliance`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DhsCompliance {
+    /// `F`
+    FullyCompliant,
+    /// `N`
+    NonCompliant,
+}
+
+/// Whether a name was truncated, `family_name_truncation` and `given_name_truncation`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Truncation {
+    /// `T`
+    Truncated,
+    /// `N`
+    NotTruncated,
+    /// `U`
+    Unknown,
+}
+
+/// `EDL_credential`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdlCredential {
+    /// `1`
+    EnhancedDriverLicence,
+    /// `2`
+    EnhancedIdentificationCard,
+}
+
+/// `race_ethnicity`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RaceEthnicity {
+    /// `AI`
+    AlaskanOrAmericanIndian,
+    /// `AP`
+    AsianOrPacificIslander,
+    /// `BK`
+    Black,
+    /// `H`
+    HispanicOrigin,
+    /// `O`
+    NonHispanic,
+    /// `U`
+    Unknown,
+    /// `W`
+    White,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DomesticDrivingPrivilege {
+    pub domestic_vehicle_class: Option<DomesticVehicleClass>,
+    #[serde(default)]
+    pub domestic_vehicle_restrictions: Vec<DomesticVehicleRestriction>,
+    #[serde(default)]
+    pub domestic_vehicle_endorsements: Vec<DomesticVehicleEndorsement>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DomesticVehicleClass {
+    pub domestic_vehicle_class_code: String,
+    pub domestic_vehicle_class_description: Option<String>,
+    #[serde(default, with = "full_date")]
+    pub issue_date: Option<Date>,
+    #[serde(default, with = "full_date")]
+    pub expiry_date: Option<Date>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DomesticVehicleRestriction {
+    pub domestic_vehicle_restriction_code: Option<String>,
+    pub domestic_vehicle_restriction_description: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DomesticVehicleEndorsement {
+    pub domestic_vehicle_endorsement_code: Option<String>,
+    pub domestic_vehicle_endorsement_description: Option<String>,
+}
+
+const NAME_SUFFIXES: &[&str] = &[
+    "JR", "SR", "1ST", "2ND", "3RD", "4TH", "5TH", "6TH", "7TH", "8TH", "9TH", "I", "II", "III",
+    "IV", "V", "VI", "VII", "VIII", "IX",
+];
+
+impl DhsCompliance {
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "F" => Some(Self::FullyCompliant),
+            "N" => Some(Self::NonCompliant),
+            _ => None,
+        }
+    }
+}
+
+impl Truncation {
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "T" => Some(Self::Truncated),
+            "N" => Some(Self::NotTruncated),
+            "U" => Some(Self::Unknown),
+            _ => None,
+        }
+    }
+}
+
+impl EdlCredential {
+    fn from_code(code: u64) -> Option<Self> {
+        match code {
+            1 => Some(Self::EnhancedDriverLicence),
+            2 => Some(Self::EnhancedIdentificationCard),
+            _ => None,
+        }
+    }
+}
+
+impl RaceEthnicity {
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "AI" => Some(Self::AlaskanOrAmericanIndian),
+            "AP" => Some(Self::AsianOrPacificIslander),
+            "BK" => Some(Self::Black),
+            "H" => Some(Self::HispanicOrigin),
+            "O" => Some(Self::NonHispanic),
+            "U" => Some(Self::Unknown),
+            "W" => Some(Self::White),
+            _ => None,
+        }
+    }
+}
+
+/// Checks the AAMVA-specific encoding of an element whose value already
+/// matches its CBOR type, converted to JSON.
+pub(crate) fn check_encoding(element: &str, value: &Value) -> Result<(), String> {
+    let valid = match element {
+        "DHS_compliance" => value.as_str().and_then(DhsCompliance::from_code).is_some(),
+        "family_name_truncation" | "given_name_truncation" => {
+            value.as_str().and_then(Truncation::from_code).is_some()
+        }
+        "race_ethnicity" => value.as_str().and_then(RaceEthnicity::from_code).is_some(),
+        "name_suffix" | "aka_suffix" => value
+            .as_str()
+            .is_some_and(|suffix| NAME_SUFFIXES.contains(&suffix)),
+        "resident_county" => value
+            .as_str()
+            .is_some_and(|county| county.len() == 3 && county.bytes().all(|b| b.is_ascii_digit())),
+        "sex" => matches!(value.as_u64(), Some(0 | 1 | 2 | 9)),
+        "weight_range" => value.as_u64().is_some_and(|range| range <= 9),
+        "EDL_credential" => value.as_u64().and_then(EdlCredential::from_code).is_some(),
+        "organ_donor" | "veteran" | "CDL_indicator" | "DHS_temporary_lawful_status" => {
+            value.as_u64() == Some(1)
+        }
+        _ => true,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("invalid AAMVA encoding {value}"))
+    }
+}
+
+impl AamvaExtensions {
+    /// Reads the AAMVA namespace of the `response` namespaces, if returned.
+    pub fn from_response(response: &BTreeMap<String, Value>) -> Option<Self> {
+        let Value::Object(elements) = response.get(AAMVA_NAMESPACE)? else {
+            return None;
+        };
+        let text = |element: &str| {
+            elements
+                .get(element)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        };
+        let uint = |element: &str| elements.get(element).and_then(Value::as_u64);
+        let flag = |element: &str| uint(element).map(|value| value == 1);
+
+        let domestic_driving_privileges = elements
+            .get("domestic_driving_privileges")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|privilege| {
+                serde_json::from_value(privilege.clone())
+                    .inspect_err(|e| debug!("Skipping invalid domestic driving privilege: {e}"))
+                    .ok()
+            })
+            .collect();
+
+        Some(Self {
+            dhs_compliance: text("DHS_compliance").and_then(|code| DhsCompliance::from_code(&code)),
+            dhs_compliance_text: text("DHS_compliance_text"),
+            dhs_temporary_lawful_status: flag("DHS_temporary_lawful_status"),
+            sex: uint("sex").and_then(|sex| sex.try_into().ok()),
+            name_suffix: text("name_suffix"),
+            family_name_truncation: text("family_name_truncation")
+                .and_then(|code| Truncation::from_code(&code)),
+            given_name_truncation: text("given_name_truncation")
+                .and_then(|code| Truncation::from_code(&code)),
+            aka_family_name: text("aka_family_name.v2"),
+            aka_given_name: text("aka_given_name.v2"),
+            aka_suffix: text("aka_suffix"),
+            organ_donor: flag("organ_donor"),
+            veteran: flag("veteran"),
+            cdl_indicator: flag("CDL_indicator"),
+            edl_credential: uint("EDL_credential").and_then(EdlCredential::from_code),
+            weight_range: uint("weight_range").and_then(|range| range.try_into().ok()),
+            race_ethnicity: text("race_ethnicity").and_then(|code| RaceEthnicity::from_code(&code)),
+            resident_county: text("resident_county"),
+            hazmat_endorsement_expiration_date: text("hazmat_endorsement_expiration_date")
+                .and_then(|date| parse_date(&date)),
+            domestic_driving_privileges,
+            aamva_version: uint("aamva_version").and_then(|version| version.try_into().ok()),
+        })
+    }
+}
@@ -8,7 +8,6 @@ use hpke::{
     Deserializable, Kem, OpModeR, Serializable, aead::AesGcm128, kdf::HkdfSha256,
     kem::DhP256HkdfSha256,
 };
-use http::StatusCode;
 use isomdl::{
     cbor,
     cose::sign1::PreparedCoseSign1,
@@ -17,7 +16,7 @@ use isomdl::{
         device_request::{DeviceRequest, DeviceRequestInfo, ItemsRequest, UseCase},
         helpers::{ByteStr, NonEmptyMap, NonEmptyVec, Tag24},
         session::SessionTranscript,
-        x509::{X5Chain, trust_anchor::TrustAnchorRegistry, x5chain::X5CHAIN_COSE_HEADER_LABEL},
+        x509::{X5Chain, x5chain::X5CHAIN_COSE_HEADER_LABEL},
     },
     presentation::{
         authentication::ResponseAuthenticationOutcome,
@@ -25,6 +24,7 @@ use isomdl::{
         reader_utils::validate_response,
     },
 };
+use openid4vp::verifier::session::{SessionStore, Status};
 use p256::ecdsa::signature::SignerMut;
 use pkcs8::DecodePrivateKey;
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
@@ -33,13 +33,25 @@ use sha2::{Digest, Sha256};
 
 use crate::{
     config::Oid4VpConfig,
+    disclosure::{RequestedElements, requested_elements},
+    error::DcApiError,
+    rules::RulePolicy,
+    session::{oid4vp_outcome, update_oid4vp_status},
     types::{DCAPINamespaceRequest, DCAPIRequestOrgIsoMDoc},
+    verdict::{PARSING_ERRORS, VerificationResult},
+    verify::{IssuerSigned, VerificationContext},
 };
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct InitiatedSessionState {
     private_key: Vec<u8>,
     session_transcript_bytes: Vec<u8>,
+    #[serde(default)]
+    requested: RequestedElements,
+    #[serde(default)]
+    rules: RulePolicy,
+    #[serde(default)]
+    doc_type: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -56,6 +68,8 @@ pub async fn initiate_inner(
     config: &Oid4VpConfig,
     req: &DCAPINamespaceRequest,
 ) -> Result<(DCAPIRequestOrgIsoMDoc, InitiatedSessionState)> {
+    config.check_origin(&req.origin)?;
+
     // Hardcoded to true to assume we don't know what the RP will do with it.
     // unwraps are safe as the original data is non-empty
     let namespaces: BTreeMap<_, _> = req
@@ -72,7 +86,7 @@ pub async fn initiate_inner(
         .collect();
     let namespaces = namespaces.try_into().unwrap();
     let items_request = ItemsRequest {
-        doc_type: "org.iso.18013.5.1.mDL".into(),
+        doc_type: req.doc_type().into(),
         namespaces,
         request_info: None,
     };
@@ -179,6 +193,9 @@ pub async fn initiate_inner(
     let session_state = InitiatedSessionState {
         private_key: private_key.to_bytes().to_vec(),
         session_transcript_bytes,
+        requested: requested_elements(req),
+        rules: req.policy.clone().unwrap_or_default(),
+        doc_type: Some(req.doc_type().to_string()),
     };
 
     Ok((res, session_state))
@@ -225,100 +242,118 @@ impl Handover {
     }
 }
 
-pub async fn submit_dc_response_inner(
+/// Verifies the response, keeping the status of the `openid4vp` session
+/// `oid4vp_session_id` initiated alongside it in `session_store` in sync:
+/// `ReceivedResponse` when processing starts, then `Complete` with the outcome.
+pub async fn submit_dc_response_inner<S: SessionStore + ?Sized>(
+    state: InitiatedSessionState,
+    dc_response: DCAPIResponseData,
+    oid4vp_session_id: &str,
+    session_store: &S,
+    ctx: &VerificationContext<'_>,
+) -> Result<VerificationResult, DcApiError> {
+    update_oid4vp_status(session_store, oid4vp_session_id, Status::ReceivedResponse).await;
+
+    let result = verify_dc_response(state, dc_response, ctx).await;
+
+    update_oid4vp_status(
+        session_store,
+        oid4vp_session_id,
+        Status::Complete(oid4vp_outcome(&result)),
+    )
+    .await;
+
+    result
+}
+
+async fn verify_dc_response(
     state: InitiatedSessionState,
-    trust_anchor_registry: TrustAnchorRegistry,
     dc_response: DCAPIResponseData,
-) -> Result<ResponseAuthenticationOutcome, (StatusCode, serde_json::Value)> {
+    ctx: &VerificationContext<'_>,
+) -> Result<VerificationResult, DcApiError> {
     let response = dc_response.response;
-    let response_bytes = BASE64_URL_SAFE_NO_PAD.decode(response).map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            json!({"error": format!("{:?}", e)}),
-        )
-    })?;
-    let encrypted_response: EncryptedResponse = cbor::from_slice(&response_bytes).map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            json!({"error": format!("{:?}", e)}),
-        )
-    })?;
+    let response_bytes = match BASE64_URL_SAFE_NO_PAD.decode(response) {
+        Ok(response_bytes) => response_bytes,
+        Err(e) => {
+            return Ok(VerificationResult::parse_failed(format!(
+                "Could not decode response: {e}"
+            )));
+        }
+    };
+    let encrypted_response: EncryptedResponse = match cbor::from_slice(&response_bytes) {
+        Ok(encrypted_response) => encrypted_response,
+        Err(e) => {
+            return Ok(VerificationResult::parse_failed(format!(
+                "Could not deserialize encrypted response: {e}"
+            )));
+        }
+    };
 
     let encapped_key = <hpke::kem::DhP256HkdfSha256 as hpke::Kem>::EncappedKey::from_bytes(
         encrypted_response.1.enc.as_ref(),
     )
-    .map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            json!({"error": format!("Could not deserialize encapped key: {:?}", e)}),
-        )
-    })?;
+    .map_err(|e| DcApiError::InvalidResponse(format!("Could not deserialize encapped key: {e}")))?;
 
     let private_key =
         <hpke::kem::DhP256HkdfSha256 as hpke::Kem>::PrivateKey::from_bytes(&state.private_key)
-            .map_err(|e| {
-                (
-                    StatusCode::BAD_REQUEST,
-                    json!({"error": format!("Could not deserialize private key: {e:?}")}),
-                )
-            })?;
+            .map_err(|e| DcApiError::Internal(format!("Could not deserialize private key: {e}")))?;
     let mut decryption_context = hpke::setup_receiver::<AesGcm128, HkdfSha256, DhP256HkdfSha256>(
         &OpModeR::Base,
         &private_key,
         &encapped_key,
         &state.session_transcript_bytes,
     )
-    .map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            json!({"error": format!("Could not set up HPKE received: {e:?}")}),
-        )
-    })?;
+    .map_err(|e| DcApiError::DecryptionFailed(format!("Could not set up HPKE receiver: {e}")))?;
     let device_response_bytes = decryption_context
         .open(encrypted_response.1.cipher_text.as_ref(), b"")
-        .map_err(|e| {
-            (
-                StatusCode::BAD_REQUEST,
-                json!({"error": format!("Could not decrypt response: {e:?}")}),
-            )
-        })?;
+        .map_err(|e| DcApiError::DecryptionFailed(e.to_string()))?;
 
-    let device_response: DeviceResponse =
-        cbor::from_slice(&device_response_bytes).map_err(|e| {
-            (
-                StatusCode::BAD_REQUEST,
-                json!({"error": format!("{:?}", e)}),
-            )
-        })?;
+    let device_response: DeviceResponse = match cbor::from_slice(&device_response_bytes) {
+        Ok(device_response) => device_response,
+        Err(e) => {
+            return Ok(VerificationResult::parse_failed(format!(
+                "Could not deserialize device response: {e}"
+            )));
+        }
+    };
     let session_transcript: SessionTranscriptDCAPI<Handover> =
         cbor::from_slice(&state.session_transcript_bytes).map_err(|e| {
-            (
-                StatusCode::BAD_REQUEST,
-                json!({
-                    "error": format!("Could not deserialize stored session transcript: {e:?}")
-                }),
-            )
+            DcApiError::Internal(format!(
+                "Could not deserialize stored session transcript: {e}"
+            ))
         })?;
 
     let mut validated_response = ResponseAuthenticationOutcome::default();
+    let mut issuer_signed = None;
     let validation_results = match parse(&device_response) {
-        Ok((document, x5chain, namespaces)) => validate_response(
-            session_transcript,
-            trust_anchor_registry,
-            x5chain,
-            document.clone(),
-            namespaces,
-        ),
+        Ok((document, x5chain, namespaces)) => {
+            issuer_signed = Some(IssuerSigned::new(&document));
+            validate_response(
+                session_transcript,
+                ctx.trust_anchor_registry.clone(),
+                x5chain,
+                document.clone(),
+                namespaces,
+            )
+        }
         Err(e) => {
             if let Ok(namespaces) = parse_namespaces(&device_response) {
                 validated_response.response = namespaces;
             }
             validated_response
                 .errors
-                .insert("parsing_errors".to_string(), json!(vec![format!("{e:?}")]));
+                .insert(PARSING_ERRORS.to_string(), json!(vec![format!("{e:?}")]));
             validated_response
         }
     };
 
-    Ok(validation_results)
+    Ok(ctx
+        .check(
+            validation_results,
+            issuer_signed,
+            state.doc_type.as_deref(),
+            &state.requested,
+            &state.rules,
+        )
+        .await)
 }
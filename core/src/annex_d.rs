@@ -1,18 +1,25 @@
 use super::client::{OID4VPClient, OID4VPVerifier};
-use crate::types::DCAPINamespaceRequest;
+use crate::{
+    config::Oid4VpConfig,
+    disclosure::{RequestedElements, requested_elements},
+    error::DcApiError,
+    rules::RulePolicy,
+    schema::EU_PID_DOC_TYPE,
+    sd_jwt::{SD_JWT_VC_FORMAT, SdJwtVcRequest, SdJwtVcVerifier, VCT_NOT_ACCEPTED},
+    session::{oid4vp_outcome, update_oid4vp_status},
+    types::DCAPINamespaceRequest,
+    verdict::{PARSING_ERRORS, PresentationResult, VerdictReason, VerificationResult},
+    verify::{IssuerSigned, VerificationContext},
+};
 
 use std::collections::HashMap;
 
 use anyhow::{Context, Result};
 use base64::prelude::*;
 use ciborium::Value as Cbor;
-use http::StatusCode;
 use isomdl::{
     cbor,
-    definitions::{
-        DeviceResponse, helpers::ByteStr, session::SessionTranscript,
-        x509::trust_anchor::TrustAnchorRegistry,
-    },
+    definitions::{DeviceResponse, helpers::ByteStr, session::SessionTranscript},
     presentation::{
         authentication::ResponseAuthenticationOutcome, reader::parse,
         reader_utils::validate_response,
@@ -32,7 +39,10 @@ use openid4vp::{
         object::UntypedObject,
     },
     utils::NonEmptyVec,
-    verifier::client::Client,
+    verifier::{
+        client::Client,
+        session::{SessionStore, Status},
+    },
 };
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use serde_json::json;
@@ -45,6 +55,24 @@ pub struct InitiatedSessionState {
     oid4vp_session_id: String,
     origin: String,
     nonce: String,
+    #[serde(default)]
+    requested: RequestedElements,
+    #[serde(default)]
+    rules: RulePolicy,
+    #[serde(default)]
+    doc_type: Option<String>,
+    #[serde(default)]
+    sd_jwt: Option<SdJwtVcRequest>,
+}
+
+/// DCQL credential query ids of the mdoc and of the SD-JWT VC alternative.
+const MDOC_QUERY_ID: &str = "0";
+const SD_JWT_QUERY_ID: &str = "1";
+
+impl InitiatedSessionState {
+    pub fn oid4vp_session_id(&self) -> &str {
+        &self.oid4vp_session_id
+    }
 }
 
 #[derive(Serialize)]
@@ -53,16 +81,19 @@ pub struct InitiateResponse {
 }
 
 pub async fn initiate_inner(
+    config: &Oid4VpConfig,
     verifier: &OID4VPVerifier,
     request: &DCAPINamespaceRequest,
     session_id: String,
 ) -> Result<(InitiateResponse, InitiatedSessionState)> {
+    config.check_origin(&request.origin)?;
+
     let mut dcql_credential_query =
-        DcqlCredentialQuery::new("0".into(), ClaimFormatDesignation::MsoMDoc);
+        DcqlCredentialQuery::new(MDOC_QUERY_ID.into(), ClaimFormatDesignation::MsoMDoc);
     dcql_credential_query.set_meta(Some(
         [(
             "doctype_value".to_string(),
-            serde_json::Value::String("org.iso.18013.5.1.mDL".to_string()),
+            serde_json::Value::String(request.doc_type().to_string()),
         )]
         .into_iter()
         .collect(),
@@ -83,13 +114,20 @@ pub async fn initiate_inner(
         }
     }
     dcql_credential_query.set_claims(Some(claims.try_into().unwrap()));
-    let mut dcql_credential_set_query =
-        DcqlCredentialSetQuery::new(NonEmptyVec::new(vec!["0".into()]));
+    let mut credential_queries = vec![dcql_credential_query];
+    let mut options = vec![vec![MDOC_QUERY_ID.to_string()]];
+    if let Some(sd_jwt) = &request.sd_jwt {
+        credential_queries.push(sd_jwt_credential_query(sd_jwt)?);
+        options.push(vec![SD_JWT_QUERY_ID.to_string()]);
+    }
+    let mut dcql_credential_set_query = DcqlCredentialSetQuery::new(options.try_into().unwrap());
     dcql_credential_set_query.set_required(None);
-    dcql_credential_set_query.set_purpose(Some(serde_json::Value::String(
-        "Authorize to the government using your mobile drivers license".into(),
-    )));
-    let mut dcql_query = DcqlQuery::new(NonEmptyVec::new(dcql_credential_query));
+    let purpose = match request.doc_type() {
+        EU_PID_DOC_TYPE => "Authorize to the government using your person identification data",
+        _ => "Authorize to the government using your mobile drivers license",
+    };
+    dcql_credential_set_query.set_purpose(Some(serde_json::Value::String(purpose.into())));
+    let mut dcql_query = DcqlQuery::new(credential_queries.try_into().unwrap());
     dcql_query.set_credential_sets(Some(NonEmptyVec::new(dcql_credential_set_query)));
     // Set the `vp_formats` parameter in the client metadata.
     let mut vp_formats = ClaimFormatMap::new();
@@ -97,6 +135,12 @@ pub async fn initiate_inner(
         ClaimFormatDesignation::MsoMDoc,
         ClaimFormatPayload::Alg(vec!["ES256".into()]),
     );
+    if request.sd_jwt.is_some() {
+        vp_formats.insert(
+            ClaimFormatDesignation::Other(SD_JWT_VC_FORMAT.into()),
+            ClaimFormatPayload::Alg(vec!["ES256".into(), "ES384".into()]),
+        );
+    }
     let mut client_metadata = UntypedObject::default();
     client_metadata.insert(VpFormats(vp_formats));
 
@@ -127,14 +171,74 @@ pub async fn initiate_inner(
         oid4vp_session_id: oid4vp_session_id.to_string(),
         origin: request.origin.clone(),
         nonce,
+        requested: requested_elements(request),
+        rules: request.policy.clone().unwrap_or_default(),
+        doc_type: Some(request.doc_type().to_string()),
+        sd_jwt: request.sd_jwt.clone(),
     };
 
     Ok((res, state))
 }
 
+/// The DCQL credential query of the SD-JWT VC alternative to the mdoc.
+fn sd_jwt_credential_query(sd_jwt: &SdJwtVcRequest) -> Result<DcqlCredentialQuery> {
+    if sd_jwt.vct_values.is_empty() {
+        return Err(
+            DcApiError::InvalidRequest("SD-JWT VC request has no vct_values".into()).into(),
+        );
+    }
+    let mut query = DcqlCredentialQuery::new(
+        SD_JWT_QUERY_ID.into(),
+        ClaimFormatDesignation::Other(SD_JWT_VC_FORMAT.into()),
+    );
+    query.set_meta(Some(
+        [("vct_values".to_string(), json!(sd_jwt.vct_values))]
+            .into_iter()
+            .collect(),
+    ));
+    let claims = sd_jwt
+        .claims
+        .iter()
+        .map(|path| {
+            let path: Vec<_> = path
+                .iter()
+                .cloned()
+                .map(DcqlCredentialClaimsQueryPath::String)
+                .collect();
+            let path = path
+                .try_into()
+                .map_err(|_| DcApiError::InvalidRequest("empty SD-JWT VC claim path".into()))?;
+            Ok(DcqlCredentialClaimsQuery::new(path))
+        })
+        .collect::<Result<Vec<_>, DcApiError>>()?;
+    let claims = claims
+        .try_into()
+        .map_err(|_| DcApiError::InvalidRequest("SD-JWT VC request has no claims".into()))?;
+    query.set_claims(Some(claims));
+    Ok(query)
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 pub struct DCAPIResponseData {
-    vp_token: HashMap<String, String>,
+    vp_token: HashMap<String, VpTokenPresentations>,
+}
+
+/// The presentations returned for a credential query: a single one in earlier
+/// drafts of OpenID4VP, an array of them in OpenID4VP 1.0.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum VpTokenPresentations {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl VpTokenPresentations {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            VpTokenPresentations::Single(presentation) => vec![presentation],
+            VpTokenPresentations::Multiple(presentations) => presentations,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -166,48 +270,280 @@ impl Handover {
     }
 }
 
-pub async fn submit_dc_response_inner(
+/// Verifies the response, keeping the status of the linked `openid4vp` session
+/// in `session_store` in sync: `ReceivedResponse` when processing starts, then
+/// `Complete` with the outcome.
+pub async fn submit_dc_response_inner<S: SessionStore + ?Sized>(
     state: InitiatedSessionState,
     client: OID4VPClient,
-    trust_anchor_registry: TrustAnchorRegistry,
     dc_response: DCAPIResponseData,
-) -> Result<ResponseAuthenticationOutcome, (StatusCode, serde_json::Value)> {
-    let first_vp_token = dc_response.vp_token.values().next().unwrap();
-    let decoded_vp_token = BASE64_URL_SAFE_NO_PAD.decode(first_vp_token).map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            json!({"error": format!("{:?}", e)}),
-        )
-    })?;
-    let device_response: DeviceResponse = cbor::from_slice(&decoded_vp_token).map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            json!({"error": format!("{:?}", e)}),
-        )
-    })?;
+    session_store: &S,
+    ctx: &VerificationContext<'_>,
+) -> Result<VerificationResult, DcApiError> {
+    let oid4vp_session_id = state.oid4vp_session_id.clone();
+    update_oid4vp_status(session_store, &oid4vp_session_id, Status::ReceivedResponse).await;
+
+    let result = verify_dc_response(state, &client.id().0, dc_response, ctx).await;
+
+    update_oid4vp_status(
+        session_store,
+        &oid4vp_session_id,
+        Status::Complete(oid4vp_outcome(&result)),
+    )
+    .await;
+
+    result
+}
+
+/// Verifies every presentation of the `vp_token`, each against the credential
+/// query of its id.
+///
+/// A presentation that cannot be decoded fails on its own, with a
+/// [Verdict::ParseFailed](crate::verdict::Verdict::ParseFailed) result.
+async fn verify_dc_response(
+    state: InitiatedSessionState,
+    client_id: &str,
+    dc_response: DCAPIResponseData,
+    ctx: &VerificationContext<'_>,
+) -> Result<VerificationResult, DcApiError> {
+    let mut vp_token = dc_response.vp_token;
+    let mut credential_ids = vec![MDOC_QUERY_ID];
+    if state.sd_jwt.is_some() {
+        credential_ids.push(SD_JWT_QUERY_ID);
+    }
+    if let Some(unknown) = vp_token
+        .keys()
+        .find(|credential_id| !credential_ids.contains(&credential_id.as_str()))
+    {
+        return Err(DcApiError::InvalidResponse(format!(
+            "vp_token has no credential query {unknown}"
+        )));
+    }
+
+    // Each credential set option is the single query of one credential, see
+    // `initiate_inner`.
+    let options: Vec<Vec<&str>> = credential_ids.iter().map(|id| vec![*id]).collect();
+    let mut presentations = Vec::new();
+    for credential_id in credential_ids {
+        let Some(tokens) = vp_token.remove(credential_id) else {
+            continue;
+        };
+        let tokens = tokens.into_vec();
+        if tokens.is_empty() {
+            return Err(DcApiError::InvalidResponse(format!(
+                "vp_token has no presentation for {credential_id}"
+            )));
+        }
+        // The credential queries do not set `multiple`, so each allows a
+        // single presentation.
+        if tokens.len() > 1 {
+            return Err(DcApiError::InvalidResponse(format!(
+                "vp_token has {} presentations for {credential_id}, whose query does not allow multiple",
+                tokens.len()
+            )));
+        }
+        for token in tokens {
+            let result = match &state.sd_jwt {
+                Some(sd_jwt) if credential_id == SD_JWT_QUERY_ID => {
+                    verify_sd_jwt(&state, sd_jwt, client_id, &token, ctx).await
+                }
+                _ => verify_mdoc(&state, client_id, &token, ctx).await?,
+            };
+            presentations.push(PresentationResult {
+                credential_id: credential_id.to_string(),
+                result,
+            });
+        }
+    }
+    VerificationResult::combine(presentations, &options)
+        .ok_or_else(|| DcApiError::InvalidResponse("vp_token is empty".into()))
+}
+
+/// Verifies a base64url-encoded mdoc `DeviceResponse`.
+async fn verify_mdoc(
+    state: &InitiatedSessionState,
+    client_id: &str,
+    presentation: &str,
+    ctx: &VerificationContext<'_>,
+) -> Result<VerificationResult, DcApiError> {
+    let decoded_vp_token = match BASE64_URL_SAFE_NO_PAD.decode(presentation) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            return Ok(VerificationResult::parse_failed(format!(
+                "Could not decode vp_token: {e}"
+            )));
+        }
+    };
+    let device_response: DeviceResponse = match cbor::from_slice(&decoded_vp_token) {
+        Ok(device_response) => device_response,
+        Err(e) => {
+            return Ok(VerificationResult::parse_failed(format!(
+                "Could not deserialize device response: {e}"
+            )));
+        }
+    };
     let mut validated_response = ResponseAuthenticationOutcome::default();
-    let handover = Handover::new(state.origin.clone(), client.id().0.clone(), state.nonce)
-        .map_err(|e| {
-            (
-                StatusCode::BAD_REQUEST,
-                json!({"error": format!("failed to create a handover: {e:?}")}),
-            )
-        })?;
+    let handover = Handover::new(
+        state.origin.clone(),
+        client_id.to_string(),
+        state.nonce.clone(),
+    )
+    .map_err(|e| DcApiError::Internal(format!("failed to create a handover: {e:#}")))?;
     let session_transcript = SessionTranscriptDCAPI::new(handover);
+    let mut issuer_signed = None;
     let validation_results = match parse(&device_response) {
-        Ok((document, x5chain, namespaces)) => validate_response(
-            session_transcript,
-            trust_anchor_registry,
-            x5chain,
-            document.clone(),
-            namespaces,
-        ),
+        Ok((document, x5chain, namespaces)) => {
+            issuer_signed = Some(IssuerSigned::new(&document));
+            validate_response(
+                session_transcript,
+                ctx.trust_anchor_registry.clone(),
+                x5chain,
+                document.clone(),
+                namespaces,
+            )
+        }
         Err(e) => {
             validated_response
                 .errors
-                .insert("parsing_errors".to_string(), json!(vec![format!("{e:?}")]));
+                .insert(PARSING_ERRORS.to_string(), json!(vec![format!("{e:?}")]));
             validated_response
         }
     };
-    Ok(validation_results)
+    Ok(ctx
+        .check(
+            validation_results,
+            issuer_signed,
+            state.doc_type.as_deref(),
+            &state.requested,
+            &state.rules,
+        )
+        .await)
+}
+
+/// Verifies an SD-JWT VC presentation, whose key binding must be to the nonce
+/// of the request and to the client id or the origin.
+async fn verify_sd_jwt(
+    state: &InitiatedSessionState,
+    sd_jwt: &SdJwtVcRequest,
+    client_id: &str,
+    presentation: &str,
+    ctx: &VerificationContext<'_>,
+) -> VerificationResult {
+    let audiences = [client_id.to_string(), format!("origin:{}", state.origin)];
+    let verifier = SdJwtVcVerifier {
+        trust_anchor_registry: ctx.trust_anchor_registry,
+        nonce: &state.nonce,
+        audiences: &audiences,
+        now: ctx.clock.now(),
+        clock_skew_seconds: ctx.policy.validity.clock_skew_seconds,
+    };
+    let (outcome, verified) = verifier.verify(presentation);
+    let vct = verified.as_ref().map(|verified| verified.vct.clone());
+    let requested = sd_jwt.requested_elements(vct.as_deref().unwrap_or_default());
+    let mut result = ctx
+        .check(
+            outcome,
+            verified.map(IssuerSigned::from_sd_jwt),
+            None,
+            &requested,
+            &state.rules,
+        )
+        .await;
+    if let Some(vct) = vct
+        && !sd_jwt.vct_values.contains(&vct)
+    {
+        result.reject(VerdictReason {
+            code: VCT_NOT_ACCEPTED.to_string(),
+            detail: json!({ "expected": sd_jwt.vct_values, "vct": vct }),
+        });
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use isomdl::definitions::x509::trust_anchor::TrustAnchorRegistry;
+    use serde_json::Value;
+    use time::OffsetDateTime;
+
+    use super::*;
+    use crate::{
+        clock::FixedClock,
+        policy::VerificationPolicy,
+        test_utils::{AUD, NONCE, NOW, TestChain, VCT, disclosure},
+        verdict::Verdict,
+    };
+
+    fn state() -> InitiatedSessionState {
+        serde_json::from_value(json!({
+            "oid4vp_session_id": "session",
+            "origin": "https://rp.example.com",
+            "nonce": NONCE,
+            "sd_jwt": { "vct_values": [VCT], "claims": [["given_name"]] },
+        }))
+        .unwrap()
+    }
+
+    /// A verifiable SD-JWT VC presentation, and the trust anchors it chains to.
+    fn sd_jwt() -> (String, TrustAnchorRegistry) {
+        let chain = TestChain::new();
+        let presentation = chain.present(&[disclosure("given_name", json!("Erika"))]);
+        (presentation, chain.registry())
+    }
+
+    async fn verify(
+        vp_token: Value,
+        registry: &TrustAnchorRegistry,
+    ) -> Result<VerificationResult, DcApiError> {
+        let policy = VerificationPolicy::default();
+        let clock = FixedClock(OffsetDateTime::from_unix_timestamp(NOW).unwrap());
+        let ctx = VerificationContext::new(registry, &policy, &clock);
+        let dc_response = serde_json::from_value(json!({ "vp_token": vp_token })).unwrap();
+        verify_dc_response(state(), AUD, dc_response, &ctx).await
+    }
+
+    #[tokio::test]
+    async fn reports_each_presentation_of_mixed_vp_token() {
+        let (presentation, registry) = sd_jwt();
+        let result = verify(
+            json!({ "0": "not an mdoc!", "1": [presentation] }),
+            &registry,
+        )
+        .await
+        .unwrap();
+
+        let presentations: Vec<(&str, &Verdict)> = result
+            .presentations
+            .iter()
+            .map(|presentation| {
+                (
+                    presentation.credential_id.as_str(),
+                    &presentation.result.verdict,
+                )
+            })
+            .collect();
+        assert!(matches!(
+            presentations[..],
+            [("0", Verdict::ParseFailed { .. }), ("1", Verdict::Verified)]
+        ));
+        // The mdoc and the SD-JWT VC are alternatives: one is enough.
+        assert!(result.verdict.is_verified(), "{:?}", result.verdict);
+    }
+
+    #[tokio::test]
+    async fn rejects_multiple_presentations_for_a_query() {
+        let (presentation, registry) = sd_jwt();
+        let result = verify(
+            json!({ "1": [presentation.clone(), presentation] }),
+            &registry,
+        )
+        .await;
+        assert!(matches!(result, Err(DcApiError::InvalidResponse(_))));
+
+        let (presentation, registry) = sd_jwt();
+        let result = verify(json!({ "1": [presentation] }), &registry)
+            .await
+            .unwrap();
+        assert!(result.verdict.is_verified(), "{:?}", result.verdict);
+    }
 }
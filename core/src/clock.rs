@@ -0,0 +1,27 @@
+use time::OffsetDateTime;
+
+/// Source of the current time for session handling and verification.
+pub trait Clock {
+    fn now(&self) -> OffsetDateTime;
+}
+
+/// The system clock, used by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+}
+
+/// A clock stopped at a given instant, e.g. to re-verify a captured response
+/// as of the time it was submitted.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedClock(pub OffsetDateTime);
+
+impl Clock for FixedClock {
+    fn now(&self) -> OffsetDateTime {
+        self.0
+    }
+}
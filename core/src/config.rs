@@ -1,8 +1,10 @@
 use anyhow::Context;
 use serde::{Deserialize, de};
-use url::Url;
+use url::{Host, Url};
 use x509_cert::{Certificate, certificate::CertificateInner};
 
+use crate::error::DcApiError;
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct Oid4VpConfig {
     #[serde(alias = "baseurl")]
@@ -10,6 +12,65 @@ pub struct Oid4VpConfig {
     pub client: Client,
     pub submission_endpoint: String,
     pub reference_endpoint: String,
+    /// Origins requests can be made for, either exact (`https://rp.example.com`)
+    /// or matching any subdomain (`https://*.example.com`).
+    ///
+    /// An empty list only allows the origin of `base_url`.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+}
+
+impl Oid4VpConfig {
+    pub fn check_origin(&self, origin: &str) -> Result<(), DcApiError> {
+        let allowed = if self.allowed_origins.is_empty() {
+            origin_matches(&self.base_url.origin().ascii_serialization(), origin)
+        } else {
+            self.allowed_origins
+                .iter()
+                .any(|allowed| origin_matches(allowed, origin))
+        };
+        if allowed {
+            return Ok(());
+        }
+        Err(DcApiError::OriginNotAllowed(origin.to_string()))
+    }
+}
+
+/// Compares the scheme, host and port of two origins, where `allowed` may
+/// match any subdomain of its host with a leading `*.`.
+fn origin_matches(allowed: &str, origin: &str) -> bool {
+    let (wildcard, allowed) = match allowed.split_once("://*.") {
+        Some((scheme, host)) => (true, format!("{scheme}://{host}")),
+        None => (false, allowed.to_string()),
+    };
+    let (Some(allowed), Some(origin)) = (parse_origin(&allowed), parse_origin(origin)) else {
+        return false;
+    };
+    if allowed.scheme() != origin.scheme()
+        || allowed.port_or_known_default() != origin.port_or_known_default()
+    {
+        return false;
+    }
+    match (allowed.host(), origin.host()) {
+        (Some(Host::Domain(allowed)), Some(Host::Domain(host))) if wildcard => host
+            .strip_suffix(allowed)
+            .and_then(|subdomain| subdomain.strip_suffix('.'))
+            .is_some_and(|subdomain| !subdomain.is_empty()),
+        (allowed, host) => !wildcard && allowed == host,
+    }
+}
+
+/// Parses an origin: a URL with a host and nothing past it but an optional
+/// trailing `/`.
+fn parse_origin(origin: &str) -> Option<Url> {
+    let url = Url::parse(origin).ok()?;
+    let is_origin = url.has_host()
+        && url.username().is_empty()
+        && url.password().is_none()
+        && url.path() == "/"
+        && url.query().is_none()
+        && url.fragment().is_none();
+    is_origin.then_some(url)
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -30,3 +91,49 @@ where
         .context("Could not load x5c")
         .map_err(de::Error::custom)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_exact_origins() {
+        let allowed = "https://rp.example.com";
+        assert!(origin_matches(allowed, "https://rp.example.com"));
+        assert!(origin_matches(allowed, "https://RP.example.com/"));
+        assert!(origin_matches(allowed, "https://rp.example.com:443"));
+        assert!(!origin_matches(allowed, "http://rp.example.com"));
+        assert!(!origin_matches(allowed, "https://rp.example.com:8443"));
+        assert!(!origin_matches(allowed, "https://rp.example.com/path"));
+        assert!(!origin_matches(allowed, "https://rp.example.com.evil.com"));
+    }
+
+    #[test]
+    fn matches_subdomains_of_wildcard_origins() {
+        let allowed = "https://*.example.com";
+        assert!(origin_matches(allowed, "https://rp.example.com"));
+        assert!(origin_matches(allowed, "https://a.rp.example.com"));
+        assert!(!origin_matches(allowed, "https://example.com"));
+        assert!(!origin_matches(allowed, "https://evilexample.com"));
+        assert!(!origin_matches(allowed, "https://evil.com#.example.com"));
+        assert!(!origin_matches(allowed, "https://evil.com?.example.com"));
+        assert!(!origin_matches(allowed, "https://evil.com/.example.com"));
+        assert!(!origin_matches(allowed, "https://rp.example.com:8443"));
+    }
+
+    #[test]
+    fn defaults_to_the_origin_of_the_base_url() {
+        let config = Oid4VpConfig {
+            base_url: Url::parse("https://verifier.example.com/oid4vp/").unwrap(),
+            client: Client {
+                x5c: Vec::new(),
+                key: String::new(),
+            },
+            submission_endpoint: String::new(),
+            reference_endpoint: String::new(),
+            allowed_origins: Vec::new(),
+        };
+        assert!(config.check_origin("https://verifier.example.com").is_ok());
+        assert!(config.check_origin("https://rp.example.com").is_err());
+    }
+}
@@ -0,0 +1,400 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use anyhow::{Context, Result, anyhow, bail};
+use pkcs8::der::{Decode, Encode};
+use serde_json::json;
+use time::OffsetDateTime;
+use tracing::debug;
+use x509_cert::{
+    Certificate,
+    crl::CertificateList,
+    ext::pkix::{
+        CrlDistributionPoints,
+        name::{DistributionPointName, GeneralName},
+    },
+};
+
+use crate::{
+    fetch::SharedFetcher,
+    signature::{SignatureEncoding, verify_ecdsa},
+    verdict::{RevocationReport, VerdictReason},
+};
+
+/// Code of the [VerdictReason] reporting a certificate listed in a CRL.
+pub const CERTIFICATE_REVOKED: &str = "certificate_revoked";
+/// Code of the [VerdictReason] reporting a certificate whose CRLs could not be checked.
+pub const CRL_UNAVAILABLE: &str = "crl_unavailable";
+
+/// Checks issuer certificates against the CRLs listed in their CRL
+/// distribution points, caching each CRL until its `nextUpdate`.
+pub struct CrlChecker {
+    fetcher: SharedFetcher,
+    cache: Mutex<HashMap<String, CachedCrl>>,
+}
+
+#[derive(Clone)]
+struct CachedCrl {
+    crl: CertificateList,
+    next_update: Option<i64>,
+}
+
+impl CrlChecker {
+    pub fn new(fetcher: SharedFetcher) -> Self {
+        Self {
+            fetcher,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Checks a certification path, ordered from the document signer to the
+    /// IACA, where each certificate is issued by the next one and the last is
+    /// self-signed.
+    ///
+    /// Certificates without CRL distribution points are not checked. The
+    /// revocation status of an empty path is unknown.
+    pub async fn check(&self, path: &[Certificate], now: OffsetDateTime) -> RevocationReport {
+        let mut report = RevocationReport::default();
+        if path.is_empty() {
+            report.unknown.push(VerdictReason {
+                code: CRL_UNAVAILABLE.to_string(),
+                detail: json!({ "detail": "the certification path is empty" }),
+            });
+        }
+        for (i, certificate) in path.iter().enumerate() {
+            let urls = distribution_points(certificate);
+            if urls.is_empty() {
+                continue;
+            }
+            let Some(issuer) = path
+                .get(i + 1)
+                .or(is_self_signed(certificate).then_some(certificate))
+            else {
+                report.unknown.push(reason(
+                    CRL_UNAVAILABLE,
+                    certificate,
+                    "issuer certificate is unknown",
+                ));
+                continue;
+            };
+
+            let mut errors = Vec::new();
+            let mut checked = false;
+            for url in urls {
+                match self.crl(&url, issuer, now).await {
+                    Ok(crl) => {
+                        checked = true;
+                        if is_listed(&crl, certificate) {
+                            report.revoked.push(reason(
+                                CERTIFICATE_REVOKED,
+                                certificate,
+                                &format!("listed in {url}"),
+                            ));
+                        }
+                        break;
+                    }
+                    Err(e) => errors.push(format!("{url}: {e:#}")),
+                }
+            }
+            if !checked {
+                report
+                    .unknown
+                    .push(reason(CRL_UNAVAILABLE, certificate, &errors.join(", ")));
+            }
+        }
+        report
+    }
+
+    /// Returns the CRL at `url`, from the cache if it is not past its `nextUpdate`.
+    async fn crl(
+        &self,
+        url: &str,
+        issuer: &Certificate,
+        now: OffsetDateTime,
+    ) -> Result<CertificateList> {
+        let now = now.unix_timestamp();
+        let cached = self
+            .cache
+            .lock()
+            .map_err(|_| anyhow!("CRL cache is poisoned"))?
+            .get(url)
+            .cloned();
+        if let Some(cached) = cached
+            && cached
+                .next_update
+                .is_some_and(|next_update| now < next_update)
+        {
+            // The cache is keyed by URL only, so the CRL must also be issued by
+            // the issuer of the certificate being checked.
+            verify_crl(&cached.crl, issuer, now)?;
+            return Ok(cached.crl);
+        }
+
+        debug!("fetching CRL from {url}");
+        let body = self.fetcher.fetch(url).await?;
+        let crl = CertificateList::from_der(&body).context("Could not decode CRL")?;
+        verify_crl(&crl, issuer, now)?;
+
+        let next_update = crl
+            .tbs_cert_list
+            .next_update
+            .map(|next_update| next_update.to_unix_duration().as_secs() as i64);
+        // CRLs without nextUpdate are fetched again on every check.
+        if next_update.is_some() {
+            self.cache
+                .lock()
+                .map_err(|_| anyhow!("CRL cache is poisoned"))?
+                .insert(
+                    url.to_string(),
+                    CachedCrl {
+                        crl: crl.clone(),
+                        next_update,
+                    },
+                );
+        }
+        Ok(crl)
+    }
+}
+
+fn verify_crl(crl: &CertificateList, issuer: &Certificate, now: i64) -> Result<()> {
+    let tbs = &crl.tbs_cert_list;
+    if tbs.issuer != issuer.tbs_certificate.subject {
+        bail!("CRL is not issued by {}", issuer.tbs_certificate.subject);
+    }
+    let message = tbs.to_der().context("Could not encode CRL")?;
+    let signature = crl
+        .signature
+        .as_bytes()
+        .context("CRL signature has unused bits")?;
+    verify_ecdsa(
+        &issuer.tbs_certificate.subject_public_key_info,
+        &message,
+        signature,
+        SignatureEncoding::Der,
+    )
+    .context("invalid CRL signature")?;
+
+    if tbs.this_update.to_unix_duration().as_secs() as i64 > now {
+        bail!("CRL is not yet valid");
+    }
+    if let Some(next_update) = tbs.next_update
+        && (next_update.to_unix_duration().as_secs() as i64) < now
+    {
+        bail!("CRL is past its nextUpdate");
+    }
+    Ok(())
+}
+
+fn is_listed(crl: &CertificateList, certificate: &Certificate) -> bool {
+    crl.tbs_cert_list
+        .revoked_certificates
+        .iter()
+        .flatten()
+        .any(|revoked| revoked.serial_number == certificate.tbs_certificate.serial_number)
+}
+
+fn is_self_signed(certificate: &Certificate) -> bool {
+    certificate.tbs_certificate.issuer == certificate.tbs_certificate.subject
+}
+
+/// The URIs of the CRL distribution points of `certificate`.
+fn distribution_points(certificate: &Certificate) -> Vec<String> {
+    certificate
+        .tbs_certificate
+        .filter::<CrlDistributionPoints>()
+        .filter_map(|r| match r {
+            Ok((_crit, points)) => Some(points.0.into_iter()),
+            Err(e) => {
+                debug!("unable to parse CrlDistributionPoints from DER: {e}");
+                None
+            }
+        })
+        .flatten()
+        .filter_map(|point| match point.distribution_point {
+            Some(DistributionPointName::FullName(names)) => Some(names),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|name| match name {
+            GeneralName::UniformResourceIdentifier(uri) => Some(uri.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn reason(code: &str, certificate: &Certificate, detail: &str) -> VerdictReason {
+    VerdictReason {
+        code: code.to_string(),
+        detail: json!({
+            "subject": certificate.tbs_certificate.subject.to_string(),
+            "serialNumber": certificate.tbs_certificate.serial_number.to_string(),
+            "detail": detail,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, sync::Arc};
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::{
+        fetch::{Fetcher, PreloadedFetcher},
+        test_utils::{CertificateSpec, NOW, TestChain, crl, key},
+    };
+
+    const DAY: i64 = 86_400;
+    const IACA_CRL: &str = "https://iaca.example/crl";
+
+    /// Serves `responses` in order, one per fetch.
+    struct SequenceFetcher {
+        responses: Mutex<VecDeque<Vec<u8>>>,
+    }
+
+    #[async_trait]
+    impl Fetcher for SequenceFetcher {
+        async fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+            self.responses
+                .lock()
+                .unwrap()
+                .pop_front()
+                .ok_or_else(|| anyhow!("no more responses for {url}"))
+        }
+    }
+
+    /// A chain whose document signer, and the IACA if `iaca_crl` is set, list
+    /// a CRL distribution point.
+    fn chain(iaca_crl: Option<&str>, ds_crl: Option<&str>) -> TestChain {
+        TestChain::issue(
+            CertificateSpec {
+                crl_url: iaca_crl,
+                ..CertificateSpec::ca("CN=Test IACA")
+            },
+            CertificateSpec {
+                crl_url: ds_crl,
+                ..CertificateSpec::leaf("CN=Test DS")
+            },
+        )
+    }
+
+    fn at(timestamp: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(timestamp).unwrap()
+    }
+
+    fn codes(reasons: &[VerdictReason]) -> Vec<&str> {
+        reasons.iter().map(|reason| reason.code.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn reports_revoked_document_signer() {
+        let chain = chain(None, Some(IACA_CRL));
+        let fetcher = PreloadedFetcher::new()
+            .with_resource(IACA_CRL, crl(&chain.iaca, &[2], NOW - DAY, NOW + DAY));
+        let report = CrlChecker::new(Arc::new(fetcher))
+            .check(&chain.path(), at(NOW))
+            .await;
+        assert_eq!(codes(&report.revoked), [CERTIFICATE_REVOKED]);
+        assert_eq!(report.revoked[0].detail["subject"], "CN=Test DS");
+        assert!(report.unknown.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_revoked_iaca() {
+        let chain = chain(Some(IACA_CRL), None);
+        let fetcher = PreloadedFetcher::new()
+            .with_resource(IACA_CRL, crl(&chain.iaca, &[1], NOW - DAY, NOW + DAY));
+        let report = CrlChecker::new(Arc::new(fetcher))
+            .check(&chain.path(), at(NOW))
+            .await;
+        assert_eq!(codes(&report.revoked), [CERTIFICATE_REVOKED]);
+        assert_eq!(report.revoked[0].detail["subject"], "CN=Test IACA");
+    }
+
+    #[tokio::test]
+    async fn accepts_certificates_not_listed() {
+        let chain = chain(None, Some(IACA_CRL));
+        let fetcher = PreloadedFetcher::new()
+            .with_resource(IACA_CRL, crl(&chain.iaca, &[7], NOW - DAY, NOW + DAY));
+        let report = CrlChecker::new(Arc::new(fetcher))
+            .check(&chain.path(), at(NOW))
+            .await;
+        assert!(report.revoked.is_empty());
+        assert!(report.unknown.is_empty());
+    }
+
+    #[tokio::test]
+    async fn refetches_crl_past_next_update() {
+        let chain = chain(None, Some(IACA_CRL));
+        let fetcher = SequenceFetcher {
+            responses: Mutex::new(VecDeque::from([
+                crl(&chain.iaca, &[], NOW - DAY, NOW + DAY),
+                crl(&chain.iaca, &[2], NOW + DAY, NOW + 3 * DAY),
+            ])),
+        };
+        let checker = CrlChecker::new(Arc::new(fetcher));
+        let path = chain.path();
+
+        // The cached CRL is used until its nextUpdate.
+        for now in [NOW, NOW + DAY / 2] {
+            let report = checker.check(&path, at(now)).await;
+            assert!(report.revoked.is_empty());
+            assert!(report.unknown.is_empty());
+        }
+
+        let report = checker.check(&path, at(NOW + 2 * DAY)).await;
+        assert_eq!(codes(&report.revoked), [CERTIFICATE_REVOKED]);
+    }
+
+    #[tokio::test]
+    async fn verifies_cached_crl_against_each_issuer() {
+        let chain = chain(None, Some(IACA_CRL));
+        let other = CertificateSpec::ca("CN=Other IACA").issue(key(3), None);
+        let other_ds = CertificateSpec {
+            crl_url: Some(IACA_CRL),
+            ..CertificateSpec::leaf("CN=Other DS")
+        }
+        .issue(key(4), Some(&other));
+        let fetcher = PreloadedFetcher::new()
+            .with_resource(IACA_CRL, crl(&chain.iaca, &[], NOW - DAY, NOW + DAY));
+        let checker = CrlChecker::new(Arc::new(fetcher));
+
+        let report = checker.check(&chain.path(), at(NOW)).await;
+        assert!(report.unknown.is_empty());
+        let report = checker
+            .check(&[other_ds.certificate, other.certificate], at(NOW))
+            .await;
+        assert_eq!(codes(&report.unknown), [CRL_UNAVAILABLE]);
+    }
+
+    #[tokio::test]
+    async fn reports_unavailable_crl() {
+        let chain = chain(None, Some(IACA_CRL));
+        let report = CrlChecker::new(Arc::new(PreloadedFetcher::new()))
+            .check(&chain.path(), at(NOW))
+            .await;
+        assert!(report.revoked.is_empty());
+        assert_eq!(codes(&report.unknown), [CRL_UNAVAILABLE]);
+    }
+
+    #[tokio::test]
+    async fn reports_crl_signed_by_another_issuer_as_unavailable() {
+        let chain = chain(None, Some(IACA_CRL));
+        let other = CertificateSpec::ca("CN=Test IACA").issue(key(3), None);
+        let fetcher = PreloadedFetcher::new()
+            .with_resource(IACA_CRL, crl(&other, &[2], NOW - DAY, NOW + DAY));
+        let report = CrlChecker::new(Arc::new(fetcher))
+            .check(&chain.path(), at(NOW))
+            .await;
+        assert!(report.revoked.is_empty());
+        assert_eq!(codes(&report.unknown), [CRL_UNAVAILABLE]);
+    }
+
+    #[tokio::test]
+    async fn reports_empty_path_as_unavailable() {
+        let report = CrlChecker::new(Arc::new(PreloadedFetcher::new()))
+            .check(&[], at(NOW))
+            .await;
+        assert_eq!(codes(&report.unknown), [CRL_UNAVAILABLE]);
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::DCAPINamespaceRequest;
+
+/// Requested data elements, by namespace.
+pub type RequestedElements = BTreeMap<String, BTreeSet<String>>;
+
+pub fn requested_elements(request: &DCAPINamespaceRequest) -> RequestedElements {
+    request
+        .namespaces
+        .iter()
+        .map(|(namespace, elements)| (namespace.clone(), elements.iter().cloned().collect()))
+        .collect()
+}
+
+/// Differences between the requested data elements and the ones returned by the wallet.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DisclosureReport {
+    /// Elements returned but not requested, by namespace.
+    pub over_disclosed: BTreeMap<String, Vec<String>>,
+    /// Requested elements the wallet withheld, by namespace.
+    pub withheld: BTreeMap<String, Vec<String>>,
+}
+
+impl DisclosureReport {
+    /// Compares `requested` to the `response` namespaces, each mapping the
+    /// returned element identifiers to their values.
+    pub fn new(
+        requested: &RequestedElements,
+        response: &BTreeMap<String, serde_json::Value>,
+    ) -> Self {
+        let returned: RequestedElements = response
+            .iter()
+            .map(|(namespace, elements)| {
+                let elements = elements
+                    .as_object()
+                    .map(|elements| elements.keys().cloned().collect())
+                    .unwrap_or_default();
+                (namespace.clone(), elements)
+            })
+            .collect();
+
+        Self {
+            over_disclosed: difference(&returned, requested),
+            withheld: difference(requested, &returned),
+        }
+    }
+
+    pub fn is_over_disclosed(&self) -> bool {
+        !self.over_disclosed.is_empty()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.withheld.is_empty()
+    }
+}
+
+/// Elements of `a` that are not in `b`, omitting empty namespaces.
+fn difference(a: &RequestedElements, b: &RequestedElements) -> BTreeMap<String, Vec<String>> {
+    a.iter()
+        .filter_map(|(namespace, elements)| {
+            let missing: Vec<String> = match b.get(namespace) {
+                Some(other) => elements.difference(other).cloned().collect(),
+                None => elements.iter().cloned().collect(),
+            };
+            (!missing.is_empty()).then(|| (namespace.clone(), missing))
+        })
+        .collect()
+}
@@ -0,0 +1,100 @@
+use http::StatusCode;
+use serde_json::json;
+
+/// Errors surfaced by the DC API flows, each with a stable machine-readable [code](DcApiError::code).
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum DcApiError {
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("invalid response: {0}")]
+    InvalidResponse(String),
+    #[error("could not decrypt response: {0}")]
+    DecryptionFailed(String),
+    #[error("origin {0} is not allowed")]
+    OriginNotAllowed(String),
+    #[error("session not found")]
+    SessionNotFound,
+    #[error("session binding mismatch: {0}")]
+    SessionBindingMismatch(String),
+    #[error("invalid session state: {0}")]
+    InvalidSessionState(String),
+    #[error("session locked")]
+    SessionLocked,
+    #[error("too many attempts: {0}")]
+    TooManyAttempts(String),
+    #[error("invalid sealed token: {0}")]
+    InvalidSealedToken(String),
+    #[error("not configured: {0}")]
+    NotConfigured(String),
+    #[error("storage error: {0}")]
+    Storage(String),
+    #[error("internal error: {0}")]
+    Internal(String),
+}
+
+impl DcApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            DcApiError::InvalidRequest(_) => "invalid_request",
+            DcApiError::InvalidResponse(_) => "invalid_response",
+            DcApiError::DecryptionFailed(_) => "decryption_failed",
+            DcApiError::OriginNotAllowed(_) => "origin_not_allowed",
+            DcApiError::SessionNotFound => "session_not_found",
+            DcApiError::SessionBindingMismatch(_) => "session_binding_mismatch",
+            DcApiError::InvalidSessionState(_) => "invalid_session_state",
+            DcApiError::SessionLocked => "session_locked",
+            DcApiError::TooManyAttempts(_) => "too_many_attempts",
+            DcApiError::InvalidSealedToken(_) => "invalid_sealed_token",
+            DcApiError::NotConfigured(_) => "not_configured",
+            DcApiError::Storage(_) => "storage_error",
+            DcApiError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            DcApiError::InvalidRequest(_)
+            | DcApiError::InvalidResponse(_)
+            | DcApiError::DecryptionFailed(_)
+            | DcApiError::InvalidSealedToken(_) => StatusCode::BAD_REQUEST,
+            DcApiError::OriginNotAllowed(_) | DcApiError::SessionBindingMismatch(_) => {
+                StatusCode::FORBIDDEN
+            }
+            DcApiError::SessionNotFound => StatusCode::NOT_FOUND,
+            DcApiError::InvalidSessionState(_) => StatusCode::CONFLICT,
+            DcApiError::SessionLocked => StatusCode::LOCKED,
+            DcApiError::TooManyAttempts(_) => StatusCode::TOO_MANY_REQUESTS,
+            DcApiError::NotConfigured(_) | DcApiError::Storage(_) | DcApiError::Internal(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    pub fn details(&self) -> serde_json::Value {
+        match self {
+            DcApiError::OriginNotAllowed(origin) => json!({ "origin": origin }),
+            DcApiError::InvalidRequest(reason)
+            | DcApiError::InvalidResponse(reason)
+            | DcApiError::DecryptionFailed(reason)
+            | DcApiError::SessionBindingMismatch(reason)
+            | DcApiError::InvalidSessionState(reason)
+            | DcApiError::TooManyAttempts(reason)
+            | DcApiError::InvalidSealedToken(reason)
+            | DcApiError::NotConfigured(reason)
+            | DcApiError::Storage(reason)
+            | DcApiError::Internal(reason) => json!({ "reason": reason }),
+            DcApiError::SessionNotFound | DcApiError::SessionLocked => serde_json::Value::Null,
+        }
+    }
+}
+
+/// Recovers a [DcApiError] propagated through [anyhow], falling back to
+/// [DcApiError::Internal] for other errors.
+impl From<anyhow::Error> for DcApiError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<DcApiError>() {
+            Ok(e) => e,
+            Err(e) => DcApiError::Internal(format!("{e:#}")),
+        }
+    }
+}
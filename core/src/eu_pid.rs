@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::Date;
+
+use crate::{
+    mdl::full_date,
+    schema::{EU_PID_NAMESPACE, is_age_over, parse_date},
+};
+
+/// The elements of an EUDI Person Identification Data mdoc, taken from the
+/// typed `eu.europa.ec.eudi.pid.1` namespace of a response.
+///
+/// Elements that were not returned, or whose value does not match the schema,
+/// are left empty.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EuPid {
+    pub family_name: Option<String>,
+    pub given_name: Option<String>,
+    #[serde(default, with = "full_date")]
+    pub birth_date: Option<Date>,
+    pub family_name_birth: Option<String>,
+    pub given_name_birth: Option<String>,
+    pub birth_place: Option<String>,
+    pub birth_country: Option<String>,
+    pub birth_state: Option<String>,
+    pub birth_city: Option<String>,
+    /// ISO 3166-1 alpha-2 codes.
+    #[serde(default)]
+    pub nationality: Vec<String>,
+    /// ISO/IEC 5218: 0 not known, 1 male, 2 female, 9 not applicable.
+    pub sex: Option<u8>,
+    pub resident_address: Option<String>,
+    pub resident_country: Option<String>,
+    pub resident_state: Option<String>,
+    pub resident_city: Option<String>,
+    pub resident_postal_code: Option<String>,
+    pub resident_street: Option<String>,
+    pub resident_house_number: Option<String>,
+    pub personal_administrative_number: Option<String>,
+    pub email_address: Option<String>,
+    pub mobile_phone_number: Option<String>,
+    /// A `data:` URL of the JPEG or JPEG 2000 portrait.
+    pub portrait: Option<String>,
+    #[serde(default, with = "full_date")]
+    pub issuance_date: Option<Date>,
+    #[serde(default, with = "full_date")]
+    pub expiry_date: Option<Date>,
+    pub issuing_authority: Option<String>,
+    pub issuing_country: Option<String>,
+    pub issuing_jurisdiction: Option<String>,
+    pub document_number: Option<String>,
+    pub administrative_number: Option<String>,
+    pub age_in_years: Option<u32>,
+    pub age_birth_year: Option<u32>,
+    /// The returned `age_over_NN` elements, by `NN`.
+    #[serde(default)]
+    pub age_over: BTreeMap<u8, bool>,
+}
+
+impl EuPid {
+    /// Reads the PID namespace of the `response` namespaces, if returned.
+    pub fn from_response(response: &BTreeMap<String, Value>) -> Option<Self> {
+        let Value::Object(elements) = response.get(EU_PID_NAMESPACE)? else {
+            return None;
+        };
+        let text = |element: &str| {
+            elements
+                .get(element)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        };
+        let date = |element: &str| {
+            elements
+                .get(element)
+                .and_then(Value::as_str)
+                .and_then(parse_date)
+        };
+        let uint = |element: &str| elements.get(element).and_then(Value::as_u64);
+
+        let nationality = elements
+            .get("nationality")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect();
+        let age_over = elements
+            .iter()
+            .filter(|(element, _)| is_age_over(element))
+            .filter_map(|(element, value)| {
+                Some((element["age_over_".len()..].parse().ok()?, value.as_bool()?))
+            })
+            .collect();
+
+        Some(Self {
+            family_name: text("family_name"),
+            given_name: text("given_name"),
+            birth_date: date("birth_date"),
+            family_name_birth: text("family_name_birth"),
+            given_name_birth: text("given_name_birth"),
+            birth_place: text("birth_place"),
+            birth_country: text("birth_country"),
+            birth_state: text("birth_state"),
+            birth_city: text("birth_city"),
+            nationality,
+            // Earlier versions of the PID Rulebook name it `gender`.
+            sex: uint("sex")
+                .or_else(|| uint("gender"))
+                .and_then(|sex| sex.try_into().ok()),
+            resident_address: text("resident_address"),
+            resident_country: text("resident_country"),
+            resident_state: text("resident_state"),
+            resident_city: text("resident_city"),
+            resident_postal_code: text("resident_postal_code"),
+            resident_street: text("resident_street"),
+            resident_house_number: text("resident_house_number"),
+            personal_administrative_number: text("personal_administrative_number"),
+            email_address: text("email_address"),
+            mobile_phone_number: text("mobile_phone_number"),
+            portrait: text("portrait"),
+            issuance_date: date("issuance_date"),
+            expiry_date: date("expiry_date"),
+            issuing_authority: text("issuing_authority"),
+            issuing_country: text("issuing_country"),
+            issuing_jurisdiction: text("issuing_jurisdiction"),
+            document_number: text("document_number"),
+            administrative_number: text("administrative_number"),
+            age_in_years: uint("age_in_years").and_then(|value| value.try_into().ok()),
+            age_birth_year: uint("age_birth_year").and_then(|value| value.try_into().ok()),
+            age_over,
+        })
+    }
+}
@@ -0,0 +1,90 @@
+use std::{future::Future, pin::Pin};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use crate::{session::SessionState, verdict::VerificationResult};
+
+/// A [SessionState] transition, as reported to [SessionEventHook]s.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionEvent {
+    pub session_id: String,
+    /// Unix timestamp in seconds.
+    pub timestamp: i64,
+    #[serde(flatten)]
+    pub kind: SessionEventKind,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum SessionEventKind {
+    Created,
+    Initiated,
+    Completed { result: VerificationResult },
+    Failed { reason: String },
+    Expired,
+    Locked,
+}
+
+impl SessionEventKind {
+    /// The `type` of the event, e.g. `completed`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SessionEventKind::Created => "created",
+            SessionEventKind::Initiated => "initiated",
+            SessionEventKind::Completed { .. } => "completed",
+            SessionEventKind::Failed { .. } => "failed",
+            SessionEventKind::Expired => "expired",
+            SessionEventKind::Locked => "locked",
+        }
+    }
+}
+
+impl SessionEvent {
+    pub fn new(session_id: String, kind: SessionEventKind, timestamp: OffsetDateTime) -> Self {
+        Self {
+            session_id,
+            timestamp: timestamp.unix_timestamp(),
+            kind,
+        }
+    }
+
+    /// The event corresponding to a session entering `state`.
+    pub fn from_state(session_id: String, state: &SessionState, timestamp: OffsetDateTime) -> Self {
+        let kind = match state {
+            SessionState::Created => SessionEventKind::Created,
+            SessionState::Initiated { .. } => SessionEventKind::Initiated,
+            SessionState::Completed(result) => SessionEventKind::Completed {
+                result: result.clone(),
+            },
+            SessionState::Failed(reason) => SessionEventKind::Failed {
+                reason: reason.clone(),
+            },
+            SessionState::Expired => SessionEventKind::Expired,
+            SessionState::Locked => SessionEventKind::Locked,
+        };
+        Self::new(session_id, kind, timestamp)
+    }
+}
+
+/// Invoked by [crate::session::SessionStorage] on every session state transition.
+///
+/// Errors are logged and do not interrupt the session flow.
+#[cfg_attr(target_arch="wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait SessionEventHook {
+    async fn on_event(&self, event: &SessionEvent) -> Result<()>;
+}
+
+/// The delivery of an event to a [SessionEventHook].
+pub type EventTask = Pin<Box<dyn Future<Output = ()>>>;
+
+/// Runs event deliveries in the background (e.g. with `spawn_local`), so
+/// that slow hooks do not hold up session state transitions, see
+/// [crate::session::SessionStorage::set_event_spawner].
+pub trait EventSpawner {
+    fn spawn(&self, task: EventTask);
+}
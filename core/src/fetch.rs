@@ -0,0 +1,47 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+
+/// Retrieves the resources revocation checks depend on, e.g. CRLs.
+#[cfg_attr(target_arch="wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait Fetcher {
+    /// Returns the body of the resource at `url`.
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>>;
+}
+
+/// A shared [Fetcher], which must be thread-safe outside of WebAssembly.
+#[cfg(not(target_arch = "wasm32"))]
+pub type SharedFetcher = Arc<dyn Fetcher + Send + Sync>;
+#[cfg(target_arch = "wasm32")]
+pub type SharedFetcher = Arc<dyn Fetcher>;
+
+/// A [Fetcher] serving resources loaded ahead of time, e.g. CRL files, for
+/// offline verification.
+#[derive(Clone, Debug, Default)]
+pub struct PreloadedFetcher {
+    resources: HashMap<String, Vec<u8>>,
+}
+
+impl PreloadedFetcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_resource(mut self, url: impl Into<String>, body: Vec<u8>) -> Self {
+        self.resources.insert(url.into(), body);
+        self
+    }
+}
+
+#[cfg_attr(target_arch="wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Fetcher for PreloadedFetcher {
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+        self.resources
+            .get(url)
+            .cloned()
+            .ok_or_else(|| anyhow!("no preloaded resource for {url}"))
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+
+use base64::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use x509_cert::{
+    Certificate,
+    der::{Encode, asn1::ObjectIdentifier},
+    name::Name,
+};
+
+use crate::verdict::VerdictReason;
+
+/// Codes of the [VerdictReason]s reporting an [IssuerPolicy] violation.
+pub const ISSUER_COUNTRY_NOT_ALLOWED: &str = "issuer_country_not_allowed";
+pub const ISSUING_COUNTRY_MISMATCH: &str = "issuing_country_mismatch";
+pub const ISSUER_NOT_ALLOWED: &str = "issuer_not_allowed";
+pub const DOC_TYPE_NOT_ALLOWED: &str = "doc_type_not_allowed";
+/// Code of the [VerdictReason] reporting a document of another doctype than requested.
+pub const DOC_TYPE_MISMATCH: &str = "doc_type_mismatch";
+pub const ANCHOR_NOT_AUTHORIZED: &str = "anchor_not_authorized";
+
+const COUNTRY_NAME: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.4.6");
+const COMMON_NAME: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.4.3");
+const ORGANIZATION_NAME: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.4.10");
+
+/// Restricts the credentials accepted by their issuer.
+///
+/// Empty allow lists allow everything, deny lists take precedence.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuerPolicy {
+    /// ISO 3166-1 alpha-2 codes, matched against the country of the document signer certificate.
+    #[serde(default)]
+    pub allowed_countries: Vec<String>,
+    #[serde(default)]
+    pub denied_countries: Vec<String>,
+    /// Matched against the issuer of the document signer certificate, either
+    /// its full distinguished name, common name or organization.
+    #[serde(default)]
+    pub allowed_issuers: Vec<String>,
+    #[serde(default)]
+    pub denied_issuers: Vec<String>,
+    /// Matched against the `docType` of the MSO.
+    #[serde(default)]
+    pub allowed_doc_types: Vec<String>,
+    #[serde(default)]
+    pub denied_doc_types: Vec<String>,
+    /// Doctypes each trust anchor, by [anchor_key_hash], is authorized to
+    /// issue. Anchors that are not listed are not restricted, but when any is
+    /// listed, credentials whose trust anchor is unknown are rejected.
+    #[serde(default)]
+    pub anchor_doc_types: BTreeMap<String, Vec<String>>,
+}
+
+/// What an [IssuerPolicy] is evaluated against.
+#[derive(Clone, Debug, Default)]
+pub struct IssuerFacts<'a> {
+    pub document_signer: Option<&'a Certificate>,
+    /// The trust anchor the document signer chains to.
+    pub anchor: Option<&'a Certificate>,
+    /// The `docType` of the MSO.
+    pub doc_type: Option<&'a str>,
+    /// The `issuing_country` data element, if disclosed.
+    pub issuing_country: Option<&'a str>,
+}
+
+impl IssuerPolicy {
+    pub fn check(&self, facts: &IssuerFacts) -> Vec<VerdictReason> {
+        let mut reasons = Vec::new();
+        let mut fail = |code: &str, detail| {
+            reasons.push(VerdictReason {
+                code: code.to_string(),
+                detail,
+            })
+        };
+
+        let country = facts
+            .document_signer
+            .and_then(|certificate| attribute(&certificate.tbs_certificate.subject, COUNTRY_NAME));
+        if !is_allowed(
+            country.as_slice(),
+            &self.allowed_countries,
+            &self.denied_countries,
+        ) {
+            fail(ISSUER_COUNTRY_NOT_ALLOWED, json!({ "country": country }));
+        }
+        // ISO/IEC 18013-5 requires issuing_country to match the document signer certificate.
+        if let (Some(country), Some(issuing_country)) = (&country, facts.issuing_country)
+            && !country.eq_ignore_ascii_case(issuing_country)
+        {
+            fail(
+                ISSUING_COUNTRY_MISMATCH,
+                json!({ "country": country, "issuingCountry": issuing_country }),
+            );
+        }
+
+        let issuer_names = facts
+            .document_signer
+            .map(|certificate| names(&certificate.tbs_certificate.issuer))
+            .unwrap_or_default();
+        if !is_allowed(&issuer_names, &self.allowed_issuers, &self.denied_issuers) {
+            fail(
+                ISSUER_NOT_ALLOWED,
+                json!({ "issuer": issuer_names.first() }),
+            );
+        }
+
+        let doc_type: Vec<String> = facts.doc_type.map(str::to_string).into_iter().collect();
+        if !is_allowed(&doc_type, &self.allowed_doc_types, &self.denied_doc_types) {
+            fail(DOC_TYPE_NOT_ALLOWED, json!({ "docType": facts.doc_type }));
+        }
+
+        if !self.anchor_doc_types.is_empty() {
+            match facts.anchor.and_then(anchor_key_hash) {
+                Some(anchor) => {
+                    if let Some(doc_types) = self.anchor_doc_types.get(&anchor)
+                        && !facts
+                            .doc_type
+                            .is_some_and(|doc_type| doc_types.iter().any(|d| d == doc_type))
+                    {
+                        fail(
+                            ANCHOR_NOT_AUTHORIZED,
+                            json!({ "anchor": anchor, "docType": facts.doc_type }),
+                        );
+                    }
+                }
+                None => fail(
+                    ANCHOR_NOT_AUTHORIZED,
+                    json!({
+                        "anchor": null,
+                        "docType": facts.doc_type,
+                        "detail": "the trust anchor could not be identified",
+                    }),
+                ),
+            }
+        }
+
+        reasons
+    }
+}
+
+/// Identifies a trust anchor in [IssuerPolicy::anchor_doc_types], as the
+/// base64 SHA-256 hash of its DER encoded SubjectPublicKeyInfo, as in HTTP
+/// public key pins.
+pub fn anchor_key_hash(anchor: &Certificate) -> Option<String> {
+    let spki = anchor
+        .tbs_certificate
+        .subject_public_key_info
+        .to_der()
+        .ok()?;
+    Some(BASE64_STANDARD.encode(Sha256::digest(spki)))
+}
+
+/// Whether any of `values` is allowed and none is denied.
+fn is_allowed(values: &[String], allowed: &[String], denied: &[String]) -> bool {
+    let matches = |list: &[String]| {
+        values
+            .iter()
+            .any(|value| list.iter().any(|entry| entry.eq_ignore_ascii_case(value)))
+    };
+    (allowed.is_empty() || matches(allowed)) && !matches(denied)
+}
+
+/// The distinguished name, common name and organization of `name`.
+fn names(name: &Name) -> Vec<String> {
+    [Some(name.to_string())]
+        .into_iter()
+        .chain([
+            attribute(name, COMMON_NAME),
+            attribute(name, ORGANIZATION_NAME),
+        ])
+        .flatten()
+        .collect()
+}
+
+fn attribute(name: &Name, oid: ObjectIdentifier) -> Option<String> {
+    name.0
+        .iter()
+        .flat_map(|rdn| rdn.0.iter())
+        .find(|attribute| attribute.oid == oid)
+        .and_then(|attribute| String::from_utf8(attribute.value.value().to_vec()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{CertificateSpec, TestChain, key};
+
+    const MDL: &str = "org.iso.18013.5.1.mDL";
+
+    fn policy(anchor: &Certificate, doc_types: &[&str]) -> IssuerPolicy {
+        IssuerPolicy {
+            anchor_doc_types: BTreeMap::from([(
+                anchor_key_hash(anchor).unwrap(),
+                doc_types.iter().map(|d| d.to_string()).collect(),
+            )]),
+            ..Default::default()
+        }
+    }
+
+    fn codes(reasons: &[VerdictReason]) -> Vec<&str> {
+        reasons.iter().map(|reason| reason.code.as_str()).collect()
+    }
+
+    #[test]
+    fn restricts_doc_types_by_anchor_key() {
+        let iaca = TestChain::new().iaca;
+        let facts = IssuerFacts {
+            anchor: Some(&iaca.certificate),
+            doc_type: Some(MDL),
+            ..Default::default()
+        };
+        assert!(policy(&iaca.certificate, &[MDL]).check(&facts).is_empty());
+        assert_eq!(
+            codes(&policy(&iaca.certificate, &["eu.europa.ec.eudi.pid.1"]).check(&facts)),
+            [ANCHOR_NOT_AUTHORIZED]
+        );
+    }
+
+    #[test]
+    fn does_not_match_anchor_by_subject() {
+        let iaca = TestChain::new().iaca;
+        // Same subject, another key.
+        let impostor = CertificateSpec::ca("CN=Test IACA").issue(key(2), None);
+        let facts = IssuerFacts {
+            anchor: Some(&impostor.certificate),
+            doc_type: Some(MDL),
+            ..Default::default()
+        };
+        assert!(
+            policy(&iaca.certificate, &["other"])
+                .check(&facts)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_anchor_when_anchors_are_restricted() {
+        let iaca = TestChain::new().iaca;
+        let facts = IssuerFacts {
+            doc_type: Some(MDL),
+            ..Default::default()
+        };
+        assert_eq!(
+            codes(&policy(&iaca.certificate, &[MDL]).check(&facts)),
+            [ANCHOR_NOT_AUTHORIZED]
+        );
+        assert!(IssuerPolicy::default().check(&facts).is_empty());
+    }
+}
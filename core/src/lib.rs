@@ -1,15 +1,40 @@
+pub mod aamva;
 pub mod annex_c;
 pub mod annex_d;
 pub mod client;
+pub mod clock;
 pub mod config;
+pub mod crl;
+pub mod disclosure;
+pub mod error;
+pub mod eu_pid;
+pub mod events;
+pub mod fetch;
+pub mod issuer;
+pub mod mdl;
+pub mod policy;
+pub mod presets;
 pub mod rng;
+pub mod rules;
+pub mod schema;
+pub mod sd_jwt;
+pub mod sealed;
 pub mod session;
+mod signature;
+pub mod status_list;
+#[cfg(test)]
+mod test_utils;
 pub mod types;
+pub mod validity;
+pub mod verdict;
+pub mod verify;
+pub mod webhook;
 pub mod x509_client;
 
 pub use http;
 pub use isomdl;
 pub use openid4vp;
 pub use openid4vp_frontend;
+pub use time;
 pub use url;
 pub use x509_cert;
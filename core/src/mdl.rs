@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::Date;
+use tracing::debug;
+
+use crate::{
+    schema::{is_age_over, parse_date},
+    verify::MDL_NAMESPACE,
+};
+
+/// The data elements of an ISO/IEC 18013-5 mobile driving licence, taken from
+/// the typed `org.iso.18013.5.1` namespace of a response.
+///
+/// Elements that were not returned, or whose value does not match the schema,
+/// are left empty.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MobileDrivingLicence {
+    pub family_name: Option<String>,
+    pub given_name: Option<String>,
+    #[serde(default, with = "full_date")]
+    pub birth_date: Option<Date>,
+    #[serde(default, with = "full_date")]
+    pub issue_date: Option<Date>,
+    #[serde(default, with = "full_date")]
+    pub expiry_date: Option<Date>,
+    pub issuing_country: Option<String>,
+    pub issuing_authority: Option<String>,
+    pub issuing_jurisdiction: Option<String>,
+    pub document_number: Option<String>,
+    pub administrative_number: Option<String>,
+    pub un_distinguishing_sign: Option<String>,
+    /// A `data:` URL of the JPEG or JPEG 2000 portrait.
+    pub portrait: Option<String>,
+    /// RFC 3339 date-time.
+    pub portrait_capture_date: Option<String>,
+    /// A `data:` URL of the signature or usual mark image.
+    pub signature_usual_mark: Option<String>,
+    #[serde(default)]
+    pub driving_privileges: Vec<DrivingPrivilege>,
+    /// ISO/IEC 5218: 0 not known, 1 male, 2 female, 9 not applicable.
+    pub sex: Option<u8>,
+    /// In centimetres.
+    pub height: Option<u32>,
+    /// In kilograms.
+    pub weight: Option<u32>,
+    pub eye_colour: Option<String>,
+    pub hair_colour: Option<String>,
+    pub birth_place: Option<String>,
+    pub nationality: Option<String>,
+    pub resident_address: Option<String>,
+    pub resident_city: Option<String>,
+    pub resident_state: Option<String>,
+    pub resident_postal_code: Option<String>,
+    pub resident_country: Option<String>,
+    pub family_name_national_character: Option<String>,
+    pub given_name_national_character: Option<String>,
+    pub age_in_years: Option<u32>,
+    pub age_birth_year: Option<u32>,
+    /// The returned `age_over_NN` elements, by `NN`.
+    #[serde(default)]
+    pub age_over: BTreeMap<u8, bool>,
+}
+
+/// A vehicle category the holder is allowed to drive, ISO/IEC 18013-5 7.2.4.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DrivingPrivilege {
+    pub vehicle_category_code: String,
+    #[serde(default, with = "full_date")]
+    pub issue_date: Option<Date>,
+    #[serde(default, with = "full_date")]
+    pub expiry_date: Option<Date>,
+    /// Restrictions and conditions.
+    #[serde(default)]
+    pub codes: Vec<DrivingPrivilegeCode>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DrivingPrivilegeCode {
+    pub code: String,
+    pub sign: Option<String>,
+    pub value: Option<String>,
+}
+
+impl MobileDrivingLicence {
+    /// Reads the mDL namespace of the `response` namespaces, if returned.
+    pub fn from_response(response: &BTreeMap<String, Value>) -> Option<Self> {
+        let Value::Object(elements) = response.get(MDL_NAMESPACE)? else {
+            return None;
+        };
+        let text = |element: &str| {
+            elements
+                .get(element)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        };
+        let date = |element: &str| {
+            elements
+                .get(element)
+                .and_then(Value::as_str)
+                .and_then(parse_date)
+        };
+        let uint = |element: &str| elements.get(element).and_then(Value::as_u64);
+
+        let driving_privileges = elements
+            .get("driving_privileges")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|privilege| {
+                serde_json::from_value(privilege.clone())
+                    .inspect_err(|e| debug!("Skipping invalid driving privilege: {e}"))
+                    .ok()
+            })
+            .collect();
+        let age_over = elements
+            .iter()
+            .filter(|(element, _)| is_age_over(element))
+            .filter_map(|(element, value)| {
+                Some((element["age_over_".len()..].parse().ok()?, value.as_bool()?))
+            })
+            .collect();
+
+        Some(Self {
+            family_name: text("family_name"),
+            given_name: text("given_name"),
+            birth_date: date("birth_date"),
+            issue_date: date("issue_date"),
+            expiry_date: date("expiry_date"),
+            issuing_country: text("issuing_country"),
+            issuing_authority: text("issuing_authority"),
+            issuing_jurisdiction: text("issuing_jurisdiction"),
+            document_number: text("document_number"),
+            administrative_number: text("administrative_number"),
+            un_distinguishing_sign: text("un_distinguishing_sign"),
+            portrait: text("portrait"),
+            portrait_capture_date: text("portrait_capture_date"),
+            signature_usual_mark: text("signature_usual_mark"),
+            driving_privileges,
+            sex: uint("sex").and_then(|value| value.try_into().ok()),
+            height: uint("height").and_then(|value| value.try_into().ok()),
+            weight: uint("weight").and_then(|value| value.try_into().ok()),
+            eye_colour: text("eye_colour"),
+            hair_colour: text("hair_colour"),
+            birth_place: text("birth_place"),
+            nationality: text("nationality"),
+            resident_address: text("resident_address"),
+            resident_city: text("resident_city"),
+            resident_state: text("resident_state"),
+            resident_postal_code: text("resident_postal_code"),
+            resident_country: text("resident_country"),
+            family_name_national_character: text("family_name_national_character"),
+            given_name_national_character: text("given_name_national_character"),
+            age_in_years: uint("age_in_years").and_then(|value| value.try_into().ok()),
+            age_birth_year: uint("age_birth_year").and_then(|value| value.try_into().ok()),
+            age_over,
+        })
+    }
+}
+
+/// (De)serializes an optional [Date] as a `YYYY-MM-DD` full-date, also
+/// accepting the date of an RFC 3339 date-time.
+pub(crate) mod full_date {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+    use time::Date;
+
+    use crate::schema::parse_date;
+
+    pub fn serialize<S: Serializer>(date: &Option<Date>, serializer: S) -> Result<S::Ok, S::Error> {
+        match date {
+            Some(date) => serializer.serialize_some(&date.to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Date>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|date| parse_date(&date).ok_or_else(|| D::Error::custom("expected a full-date")))
+            .transpose()
+    }
+}
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{issuer::IssuerPolicy, validity::ValidityPolicy};
+
+/// Checks applied to a response on top of its authentication.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationPolicy {
+    /// Reject responses disclosing data elements that were not requested.
+    #[serde(default)]
+    pub strict_disclosure: bool,
+    /// Reject responses with data elements that do not match the schema of
+    /// their namespace, including dates without their CBOR tag.
+    #[serde(default)]
+    pub strict_schema: bool,
+    #[serde(default)]
+    pub validity: ValidityPolicy,
+    #[serde(default)]
+    pub issuer: IssuerPolicy,
+    /// Accept responses whose revocation status could not be determined,
+    /// e.g. as a CRL could not be fetched.
+    #[serde(default)]
+    pub soft_fail_revocation: bool,
+}
@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    schema::{AAMVA_NAMESPACE, EU_PID_DOC_TYPE, EU_PID_NAMESPACE, MDL_DOC_TYPE},
+    verify::MDL_NAMESPACE,
+};
+
+/// Predefined sets of data elements to request, by namespace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestPreset {
+    /// The mandatory elements of an ISO/IEC 18013-5 mDL.
+    Mdl,
+    /// `age_over_21` and the portrait to match it to the holder.
+    AgeOver21,
+    /// The mDL elements, with the AAMVA elements US issuers return.
+    Aamva,
+    /// The elements needed to accept a REAL ID compliant credential.
+    RealId,
+    /// The mandatory elements of an EUDI PID.
+    EuPid,
+    /// `age_over_18` of an EUDI PID.
+    EuPidAgeOver18,
+}
+
+const MDL_ELEMENTS: &[&str] = &[
+    "family_name",
+    "given_name",
+    "birth_date",
+    "issue_date",
+    "expiry_date",
+    "issuing_country",
+    "issuing_authority",
+    "document_number",
+    "portrait",
+    "driving_privileges",
+    "un_distinguishing_sign",
+];
+
+const AAMVA_ELEMENTS: &[&str] = &[
+    "DHS_compliance",
+    "sex",
+    "name_suffix",
+    "veteran",
+    "organ_donor",
+    "EDL_credential",
+    "resident_county",
+    "domestic_driving_privileges",
+];
+
+const EU_PID_ELEMENTS: &[&str] = &[
+    "family_name",
+    "given_name",
+    "birth_date",
+    "birth_place",
+    "nationality",
+    "expiry_date",
+    "issuing_authority",
+    "issuing_country",
+];
+
+impl RequestPreset {
+    /// The doctype of the requested credential.
+    pub fn doc_type(self) -> &'static str {
+        match self {
+            RequestPreset::EuPid | RequestPreset::EuPidAgeOver18 => EU_PID_DOC_TYPE,
+            _ => MDL_DOC_TYPE,
+        }
+    }
+
+    /// The elements to request, by namespace.
+    pub fn namespaces(self) -> BTreeMap<String, Vec<String>> {
+        let namespaces: &[(&str, &[&str])] = match self {
+            RequestPreset::Mdl => &[(MDL_NAMESPACE, MDL_ELEMENTS)],
+            RequestPreset::AgeOver21 => &[(MDL_NAMESPACE, &["age_over_21", "portrait"])],
+            RequestPreset::Aamva => &[
+                (MDL_NAMESPACE, MDL_ELEMENTS),
+                (AAMVA_NAMESPACE, AAMVA_ELEMENTS),
+            ],
+            RequestPreset::RealId => &[
+                (
+                    MDL_NAMESPACE,
+                    &[
+                        "family_name",
+                        "given_name",
+                        "birth_date",
+                        "expiry_date",
+                        "issuing_authority",
+                        "document_number",
+                        "portrait",
+                    ],
+                ),
+                (
+                    AAMVA_NAMESPACE,
+                    &["DHS_compliance", "DHS_temporary_lawful_status"],
+                ),
+            ],
+            RequestPreset::EuPid => &[(EU_PID_NAMESPACE, EU_PID_ELEMENTS)],
+            RequestPreset::EuPidAgeOver18 => &[(EU_PID_NAMESPACE, &["age_over_18"])],
+        };
+        namespaces
+            .iter()
+            .map(|(namespace, elements)| {
+                (
+                    namespace.to_string(),
+                    elements.iter().map(|element| element.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+}
@@ -1,3 +1,4 @@
+use base64::prelude::*;
 use hpke::rand_core::{CryptoRng, RngCore};
 
 /// A cryptographically secure random number generator that uses getrandom.
@@ -28,3 +29,13 @@ impl CryptoRng for GetRandomRng {}
 pub fn rng() -> GetRandomRng {
     GetRandomRng
 }
+
+/// Length in bytes of the secrets generated by [random_secret].
+pub const SECRET_LEN: usize = 32;
+
+/// Generates a 256-bit random secret, base64url encoded.
+pub fn random_secret() -> String {
+    let mut bytes = [0u8; SECRET_LEN];
+    rng().fill_bytes(&mut bytes);
+    BASE64_URL_SAFE_NO_PAD.encode(bytes)
+}
@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use time::{Duration, OffsetDateTime};
+
+use crate::{schema::parse_date, verdict::VerdictReason, verify::MDL_NAMESPACE};
+
+/// Code of the [VerdictReason]s reporting a failed [Rule].
+pub const POLICY_RULE_FAILED: &str = "policy_rule_failed";
+
+/// Declarative rules evaluated against the data elements of a response, e.g.
+/// `age_over_21 == true and issuing_country in [US, CA]`.
+///
+/// Every rule must hold. Policies are written in JSON or TOML:
+///
+/// ```toml
+/// [[rules]]
+/// element = { element = "age_over_21", equals = true }
+///
+/// [[rules]]
+/// element = { element = "issuing_country", in = ["US", "CA"] }
+///
+/// [[rules]]
+/// element = { element = "issue_date", maxAgeDays = 1826, description = "issued within 5 years" }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RulePolicy {
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Rule {
+    /// Holds if every rule holds.
+    All(Vec<Rule>),
+    /// Holds if at least one rule holds.
+    Any(Vec<Rule>),
+    Not(Box<Rule>),
+    Element(ElementRule),
+}
+
+/// Conditions on a single data element, which must be disclosed. Every
+/// condition that is set must hold.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementRule {
+    /// Defaults to the mDL namespace, `org.iso.18013.5.1`.
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    pub element: String,
+    /// Included in the [RuleFailure] when the rule fails.
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub equals: Option<Value>,
+    #[serde(default, rename = "in")]
+    pub one_of: Option<Vec<Value>>,
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+    /// For full-date elements, the most days the date may be in the past.
+    #[serde(default)]
+    pub max_age_days: Option<i64>,
+}
+
+fn default_namespace() -> String {
+    MDL_NAMESPACE.to_string()
+}
+
+/// Explains why a [Rule] does not hold.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleFailure {
+    /// Where the rule is in the policy, e.g. `rules[1].any[0]`.
+    pub rule: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// e.g. `org.iso.18013.5.1/issuing_country is "MX", expected one of ["US","CA"]`.
+    pub explanation: String,
+}
+
+impl From<RuleFailure> for VerdictReason {
+    fn from(failure: RuleFailure) -> Self {
+        VerdictReason {
+            code: POLICY_RULE_FAILED.to_string(),
+            detail: json!(failure),
+        }
+    }
+}
+
+impl RulePolicy {
+    pub fn from_json(source: &str) -> Result<Self> {
+        serde_json::from_str(source).context("Could not parse JSON policy")
+    }
+
+    pub fn from_toml(source: &str) -> Result<Self> {
+        toml::from_str(source).context("Could not parse TOML policy")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Evaluates the rules against the `response` namespaces, each mapping the
+    /// returned element identifiers to their values.
+    pub fn evaluate(
+        &self,
+        response: &BTreeMap<String, Value>,
+        now: OffsetDateTime,
+    ) -> Vec<RuleFailure> {
+        self.rules
+            .iter()
+            .enumerate()
+            .flat_map(|(i, rule)| rule.evaluate(&format!("rules[{i}]"), response, now))
+            .collect()
+    }
+}
+
+impl Rule {
+    fn evaluate(
+        &self,
+        path: &str,
+        response: &BTreeMap<String, Value>,
+        now: OffsetDateTime,
+    ) -> Vec<RuleFailure> {
+        match self {
+            Rule::All(rules) => rules
+                .iter()
+                .enumerate()
+                .flat_map(|(i, rule)| rule.evaluate(&format!("{path}.all[{i}]"), response, now))
+                .collect(),
+            Rule::Any(rules) => {
+                let mut explanations = Vec::new();
+                for (i, rule) in rules.iter().enumerate() {
+                    let failures = rule.evaluate(&format!("{path}.any[{i}]"), response, now);
+                    if failures.is_empty() {
+                        return Vec::new();
+                    }
+                    explanations.extend(failures.into_iter().map(|f| f.explanation));
+                }
+                vec![RuleFailure {
+                    rule: path.to_string(),
+                    description: None,
+                    explanation: format!("none of: {}", explanations.join("; ")),
+                }]
+            }
+            Rule::Not(rule) => {
+                if rule
+                    .evaluate(&format!("{path}.not"), response, now)
+                    .is_empty()
+                {
+                    vec![RuleFailure {
+                        rule: path.to_string(),
+                        description: None,
+                        explanation: "negated rule holds".to_string(),
+                    }]
+                } else {
+                    Vec::new()
+                }
+            }
+            Rule::Element(rule) => rule
+                .explain(response, now)
+                .map(|explanation| RuleFailure {
+                    rule: path.to_string(),
+                    description: rule.description.clone(),
+                    explanation,
+                })
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+impl ElementRule {
+    /// Why the rule does not hold, if it does not.
+    fn explain(&self, response: &BTreeMap<String, Value>, now: OffsetDateTime) -> Option<String> {
+        let name = format!("{}/{}", self.namespace, self.element);
+        let Some(value) = response
+            .get(&self.namespace)
+            .and_then(|elements| elements.get(&self.element))
+        else {
+            return Some(format!("{name} was not disclosed"));
+        };
+
+        if let Some(expected) = &self.equals
+            && value != expected
+        {
+            return Some(format!("{name} is {value}, expected {expected}"));
+        }
+        if let Some(expected) = &self.one_of
+            && !expected.contains(value)
+        {
+            return Some(format!(
+                "{name} is {value}, expected one of {}",
+                json!(expected)
+            ));
+        }
+        if self.min.is_some() || self.max.is_some() {
+            let Some(number) = value.as_f64() else {
+                return Some(format!("{name} is {value}, expected a number"));
+            };
+            if let Some(min) = self.min
+                && number < min
+            {
+                return Some(format!("{name} is {number}, expected at least {min}"));
+            }
+            if let Some(max) = self.max
+                && number > max
+            {
+                return Some(format!("{name} is {number}, expected at most {max}"));
+            }
+        }
+        if let Some(max_age_days) = self.max_age_days {
+            let Some(date) = value.as_str().and_then(parse_date) else {
+                return Some(format!("{name} is {value}, expected a full-date"));
+            };
+            if date < (now - Duration::days(max_age_days)).date() {
+                return Some(format!(
+                    "{name} is {date}, more than {max_age_days} days ago"
+                ));
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,487 @@
+use std::collections::BTreeMap;
+
+use base64::prelude::*;
+use ciborium::Value as Cbor;
+use isomdl::definitions::device_response::Document;
+use serde_json::{Map, Value, json};
+use time::{Date, Month};
+
+use crate::{aamva, verify::MDL_NAMESPACE};
+
+/// Code of the [VerdictReason](crate::verdict::VerdictReason)s reporting a
+/// data element whose value does not match its schema.
+pub const INVALID_ELEMENT: &str = "invalid_element";
+
+/// The AAMVA extensions to the mDL namespace.
+pub const AAMVA_NAMESPACE: &str = "org.iso.18013.5.1.aamva";
+/// The EUDI Person Identification Data namespace.
+pub const EU_PID_NAMESPACE: &str = "eu.europa.ec.eudi.pid.1";
+
+/// The ISO/IEC 18013-5 mDL doctype.
+pub const MDL_DOC_TYPE: &str = "org.iso.18013.5.1.mDL";
+/// The EUDI PID doctype, which is also the name of its namespace.
+pub const EU_PID_DOC_TYPE: &str = "eu.europa.ec.eudi.pid.1";
+
+/// CBOR tag of an RFC 3339 date-time, `tdate`.
+const TDATE_TAG: u64 = 0;
+/// CBOR tag of an RFC 8943 `full-date`.
+const FULL_DATE_TAG: u64 = 1004;
+
+/// Issuer-signed data element values, by namespace and identifier.
+pub type IssuerElements = BTreeMap<String, BTreeMap<String, Cbor>>;
+
+/// The encoding of a data element value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ElementType {
+    Text,
+    /// A `tstr` or an array of them, converted to an array.
+    Texts,
+    Bytes,
+    Bool,
+    Uint,
+    /// `full-date`, converted to `YYYY-MM-DD`.
+    FullDate,
+    /// `tdate`, converted to an RFC 3339 date-time.
+    DateTime,
+    /// `tdate` or `full-date`.
+    Date,
+    /// A JPEG or JPEG 2000 `bstr`, converted to a data URL.
+    Image,
+    /// The `driving_privileges` structure of ISO/IEC 18013-5 7.2.4.
+    DrivingPrivileges,
+    /// The AAMVA `domestic_driving_privileges` structure.
+    DomesticDrivingPrivileges,
+}
+
+/// The type of a data element, for the namespaces with a known schema.
+pub fn element_type(namespace: &str, element: &str) -> Option<ElementType> {
+    match namespace {
+        MDL_NAMESPACE => mdl_element_type(element),
+        AAMVA_NAMESPACE => aamva_element_type(element),
+        EU_PID_NAMESPACE => eu_pid_element_type(element),
+        _ => None,
+    }
+}
+
+/// ISO/IEC 18013-5 Table 5.
+fn mdl_element_type(element: &str) -> Option<ElementType> {
+    use ElementType::*;
+
+    Some(match element {
+        "family_name"
+        | "given_name"
+        | "issuing_country"
+        | "issuing_authority"
+        | "document_number"
+        | "un_distinguishing_sign"
+        | "administrative_number"
+        | "eye_colour"
+        | "hair_colour"
+        | "birth_place"
+        | "resident_address"
+        | "issuing_jurisdiction"
+        | "nationality"
+        | "resident_city"
+        | "resident_state"
+        | "resident_postal_code"
+        | "resident_country"
+        | "family_name_national_character"
+        | "given_name_national_character" => Text,
+        "birth_date" => FullDate,
+        "issue_date" | "expiry_date" => Date,
+        "portrait_capture_date" => DateTime,
+        "portrait" | "signature_usual_mark" => Image,
+        "driving_privileges" => DrivingPrivileges,
+        "sex" | "height" | "weight" | "age_in_years" | "age_birth_year" => Uint,
+        _ if is_age_over(element) => Bool,
+        _ if element.starts_with("biometric_template_") => Bytes,
+        _ => return None,
+    })
+}
+
+/// AAMVA mDL Implementation Guidelines, namespace `org.iso.18013.5.1.aamva`.
+fn aamva_element_type(element: &str) -> Option<ElementType> {
+    use ElementType::*;
+
+    Some(match element {
+        "domestic_driving_privileges" => DomesticDrivingPrivileges,
+        "name_suffix"
+        | "family_name_truncation"
+        | "given_name_truncation"
+        | "aka_family_name.v2"
+        | "aka_given_name.v2"
+        | "aka_suffix"
+        | "race_ethnicity"
+        | "DHS_compliance"
+        | "DHS_compliance_text"
+        | "resident_county"
+        | "audit_information" => Text,
+        "aamva_version"
+        | "organ_donor"
+        | "veteran"
+        | "weight_range"
+        | "EDL_credential"
+        | "sex"
+        | "CDL_indicator"
+        | "DHS_temporary_lawful_status" => Uint,
+        "hazmat_endorsement_expiration_date" => FullDate,
+        _ => return None,
+    })
+}
+
+/// EUDI PID Rulebook, mdoc encoding.
+fn eu_pid_element_type(element: &str) -> Option<ElementType> {
+    use ElementType::*;
+
+    Some(match element {
+        "family_name"
+        | "given_name"
+        | "family_name_birth"
+        | "given_name_birth"
+        | "birth_place"
+        | "birth_country"
+        | "birth_state"
+        | "birth_city"
+        | "resident_address"
+        | "resident_country"
+        | "resident_state"
+        | "resident_city"
+        | "resident_postal_code"
+        | "resident_street"
+        | "resident_house_number"
+        | "issuing_authority"
+        | "document_number"
+        | "administrative_number"
+        | "issuing_country"
+        | "issuing_jurisdiction"
+        | "personal_administrative_number"
+        | "email_address"
+        | "mobile_phone_number"
+        | "trust_anchor" => Text,
+        "nationality" => Texts,
+        "birth_date" => FullDate,
+        "issuance_date" | "expiry_date" => Date,
+        "gender" | "sex" | "age_in_years" | "age_birth_year" => Uint,
+        "portrait" => Image,
+        _ if is_age_over(element) => Bool,
+        _ => return None,
+    })
+}
+
+/// Whether `element` is an `age_over_NN` attestation.
+pub fn is_age_over(element: &str) -> bool {
+    element
+        .strip_prefix("age_over_")
+        .is_some_and(|age| age.len() == 2 && age.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Collects the issuer-signed data element values of `document`.
+pub fn issuer_elements(document: &Document) -> IssuerElements {
+    let mut elements = IssuerElements::new();
+    let Some(namespaces) = &document.issuer_signed.namespaces else {
+        return elements;
+    };
+    for (namespace, items) in namespaces.iter() {
+        let values = elements.entry(namespace.clone()).or_default();
+        for item in items.iter() {
+            let item = item.as_ref();
+            values.insert(item.element_identifier.clone(), item.element_value.clone());
+        }
+    }
+    elements
+}
+
+/// Converts a data element value to JSON, validating it against its schema if
+/// it has one. Values without a schema are converted as is.
+///
+/// Under `strict` validation, `tdate` and `full-date` values must carry their
+/// CBOR tag.
+pub fn element_to_json(
+    namespace: &str,
+    element: &str,
+    value: &Cbor,
+    strict: bool,
+) -> Result<Value, String> {
+    let Some(element_type) = element_type(namespace, element) else {
+        return Ok(cbor_to_json(value));
+    };
+    let value = typed_to_json(element_type, value, strict)?;
+    if namespace == AAMVA_NAMESPACE {
+        aamva::check_encoding(element, &value)?;
+    }
+    Ok(value)
+}
+
+/// Converts `value`, which must be of type `element_type`, to JSON, see
+/// [element_to_json] for `strict`.
+pub fn typed_to_json(
+    element_type: ElementType,
+    value: &Cbor,
+    strict: bool,
+) -> Result<Value, String> {
+    match (element_type, value) {
+        (ElementType::Text, Cbor::Text(text)) => Ok(json!(text)),
+        (ElementType::Texts, Cbor::Text(text)) => Ok(json!([text])),
+        (ElementType::Texts, Cbor::Array(values)) => values
+            .iter()
+            .map(|value| typed_to_json(ElementType::Text, value, strict))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        (ElementType::Bytes, Cbor::Bytes(bytes)) => Ok(json!(BASE64_URL_SAFE_NO_PAD.encode(bytes))),
+        (ElementType::Bool, Cbor::Bool(value)) => Ok(json!(value)),
+        (ElementType::Uint, Cbor::Integer(value)) => u64::try_from(*value)
+            .map(|value| json!(value))
+            .map_err(|_| "expected an unsigned integer".to_string()),
+        (ElementType::FullDate, value) => full_date(value, strict).map(|date| json!(date)),
+        (ElementType::DateTime, value) => date_time(value, strict).map(|date| json!(date)),
+        (ElementType::Date, value) => date_time(value, strict)
+            .or_else(|_| full_date(value, strict))
+            .map(|date| json!(date))
+            .map_err(|_| "expected a tdate or full-date".to_string()),
+        (ElementType::Image, Cbor::Bytes(bytes)) => image(bytes).map(|image| json!(image)),
+        (ElementType::DrivingPrivileges, Cbor::Array(privileges)) => privileges
+            .iter()
+            .map(|privilege| driving_privilege(privilege, strict))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        (ElementType::DomesticDrivingPrivileges, Cbor::Array(privileges)) => privileges
+            .iter()
+            .map(|privilege| domestic_driving_privilege(privilege, strict))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        (element_type, _) => Err(format!("expected {element_type:?}")),
+    }
+}
+
+/// Converts a CBOR value to JSON, unwrapping tags and encoding byte strings
+/// in base64url.
+pub fn cbor_to_json(value: &Cbor) -> Value {
+    match value {
+        Cbor::Integer(value) => {
+            let value = i128::from(*value);
+            i64::try_from(value)
+                .map(|value| json!(value))
+                .or_else(|_| u64::try_from(value).map(|value| json!(value)))
+                .unwrap_or_else(|_| json!(value.to_string()))
+        }
+        Cbor::Bytes(bytes) => json!(BASE64_URL_SAFE_NO_PAD.encode(bytes)),
+        Cbor::Float(value) => json!(value),
+        Cbor::Text(text) => json!(text),
+        Cbor::Bool(value) => json!(value),
+        Cbor::Null => Value::Null,
+        Cbor::Tag(_, value) => cbor_to_json(value),
+        Cbor::Array(values) => Value::Array(values.iter().map(cbor_to_json).collect()),
+        Cbor::Map(entries) => Value::Object(
+            entries
+                .iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        Cbor::Text(key) => key.clone(),
+                        key => cbor_to_json(key).to_string(),
+                    };
+                    (key, cbor_to_json(value))
+                })
+                .collect(),
+        ),
+        _ => Value::Null,
+    }
+}
+
+fn full_date(value: &Cbor, strict: bool) -> Result<String, String> {
+    let text = match value {
+        Cbor::Tag(FULL_DATE_TAG, value) => value.as_text(),
+        Cbor::Text(_) if strict => return Err("expected a full-date with tag 1004".to_string()),
+        value => value.as_text(),
+    };
+    match text {
+        Some(text) if text.len() == 10 && parse_date(text).is_some() => Ok(text.to_string()),
+        _ => Err("expected a full-date".to_string()),
+    }
+}
+
+fn date_time(value: &Cbor, strict: bool) -> Result<String, String> {
+    let text = match value {
+        Cbor::Tag(TDATE_TAG, value) => value.as_text(),
+        Cbor::Text(_) if strict => return Err("expected a tdate with tag 0".to_string()),
+        value => value.as_text(),
+    };
+    match text {
+        Some(text)
+            if text.len() >= 20
+                && text.as_bytes()[10].eq_ignore_ascii_case(&b'T')
+                && parse_date(text).is_some() =>
+        {
+            Ok(text.to_string())
+        }
+        _ => Err("expected a tdate".to_string()),
+    }
+}
+
+/// Parses the `YYYY-MM-DD` prefix of a full-date or date-time.
+pub(crate) fn parse_date(value: &str) -> Option<Date> {
+    let mut parts = value.get(..10)?.split('-');
+    let year = parts.next()?.parse().ok()?;
+    let month = Month::try_from(parts.next()?.parse::<u8>().ok()?).ok()?;
+    let day = parts.next()?.parse().ok()?;
+    Date::from_calendar_date(year, month, day).ok()
+}
+
+fn image(bytes: &[u8]) -> Result<String, String> {
+    let media_type = if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "image/jpeg"
+    } else if bytes.starts_with(&[0x00, 0x00, 0x00, 0x0C, 0x6A, 0x50, 0x20, 0x20])
+        || bytes.starts_with(&[0xFF, 0x4F, 0xFF, 0x51])
+    {
+        "image/jp2"
+    } else {
+        return Err("expected a JPEG or JPEG 2000 image".to_string());
+    };
+    Ok(format!(
+        "data:{media_type};base64,{}",
+        BASE64_STANDARD.encode(bytes)
+    ))
+}
+
+fn driving_privilege(value: &Cbor, strict: bool) -> Result<Value, String> {
+    let entries = value
+        .as_map()
+        .ok_or_else(|| "expected driving privileges to be maps".to_string())?;
+    let mut privilege = Map::new();
+    for (key, value) in entries {
+        let Some(key) = key.as_text() else {
+            return Err("expected driving privilege keys to be text".to_string());
+        };
+        let value = match key {
+            "vehicle_category_code" => typed_to_json(ElementType::Text, value, strict),
+            "issue_date" | "expiry_date" => typed_to_json(ElementType::FullDate, value, strict),
+            "codes" => value
+                .as_array()
+                .ok_or_else(|| "expected driving privilege codes to be an array".to_string())?
+                .iter()
+                .map(|code| driving_privilege_code(code, strict))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+            _ => Ok(cbor_to_json(value)),
+        }
+        .map_err(|e| format!("{key}: {e}"))?;
+        privilege.insert(key.to_string(), value);
+    }
+    if !privilege.contains_key("vehicle_category_code") {
+        return Err("driving privilege without vehicle_category_code".to_string());
+    }
+    Ok(Value::Object(privilege))
+}
+
+fn driving_privilege_code(value: &Cbor, strict: bool) -> Result<Value, String> {
+    let entries = value
+        .as_map()
+        .ok_or_else(|| "expected codes to be maps".to_string())?;
+    let mut code = Map::new();
+    for (key, value) in entries {
+        let Some(key) = key.as_text() else {
+            return Err("expected code keys to be text".to_string());
+        };
+        let value = match key {
+            "code" | "sign" | "value" => typed_to_json(ElementType::Text, value, strict)
+                .map_err(|e| format!("{key}: {e}"))?,
+            _ => cbor_to_json(value),
+        };
+        code.insert(key.to_string(), value);
+    }
+    if !code.contains_key("code") {
+        return Err("driving privilege code without code".to_string());
+    }
+    Ok(Value::Object(code))
+}
+
+fn domestic_driving_privilege(value: &Cbor, strict: bool) -> Result<Value, String> {
+    let entries = value
+        .as_map()
+        .ok_or_else(|| "expected domestic driving privileges to be maps".to_string())?;
+    let mut privilege = Map::new();
+    for (key, value) in entries {
+        let Some(key) = key.as_text() else {
+            return Err("expected domestic driving privilege keys to be text".to_string());
+        };
+        let value = match key {
+            "domestic_vehicle_class" => domestic_vehicle_class(value, strict),
+            "domestic_vehicle_restrictions" | "domestic_vehicle_endorsements" => value
+                .as_array()
+                .filter(|entries| entries.iter().all(|entry| entry.as_map().is_some()))
+                .map(|_| cbor_to_json(value))
+                .ok_or_else(|| "expected an array of maps".to_string()),
+            _ => Ok(cbor_to_json(value)),
+        }
+        .map_err(|e| format!("{key}: {e}"))?;
+        privilege.insert(key.to_string(), value);
+    }
+    Ok(Value::Object(privilege))
+}
+
+fn domestic_vehicle_class(value: &Cbor, strict: bool) -> Result<Value, String> {
+    let entries = value.as_map().ok_or_else(|| "expected a map".to_string())?;
+    let mut class = Map::new();
+    for (key, value) in entries {
+        let Some(key) = key.as_text() else {
+            return Err("expected domestic vehicle class keys to be text".to_string());
+        };
+        let value = match key {
+            "domestic_vehicle_class_code" | "domestic_vehicle_class_description" => {
+                typed_to_json(ElementType::Text, value, strict)
+            }
+            "issue_date" | "expiry_date" => typed_to_json(ElementType::FullDate, value, strict),
+            _ => Ok(cbor_to_json(value)),
+        }
+        .map_err(|e| format!("{key}: {e}"))?;
+        class.insert(key.to_string(), value);
+    }
+    if !class.contains_key("domestic_vehicle_class_code") {
+        return Err("domestic vehicle class without domestic_vehicle_class_code".to_string());
+    }
+    Ok(Value::Object(class))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> Cbor {
+        Cbor::Text(value.to_string())
+    }
+
+    #[test]
+    fn requires_date_tags_when_strict() {
+        let birth_date = Cbor::Tag(FULL_DATE_TAG, Box::new(text("1990-01-31")));
+        let signed = Cbor::Tag(TDATE_TAG, Box::new(text("2025-01-01T00:00:00Z")));
+        for strict in [false, true] {
+            assert_eq!(
+                element_to_json(MDL_NAMESPACE, "birth_date", &birth_date, strict),
+                Ok(json!("1990-01-31"))
+            );
+            assert_eq!(
+                typed_to_json(ElementType::DateTime, &signed, strict),
+                Ok(json!("2025-01-01T00:00:00Z"))
+            );
+        }
+
+        assert!(element_to_json(MDL_NAMESPACE, "birth_date", &text("1990-01-31"), false).is_ok());
+        assert_eq!(
+            element_to_json(MDL_NAMESPACE, "birth_date", &text("1990-01-31"), true),
+            Err("expected a full-date with tag 1004".to_string())
+        );
+        assert_eq!(
+            typed_to_json(ElementType::DateTime, &text("2025-01-01T00:00:00Z"), true),
+            Err("expected a tdate with tag 0".to_string())
+        );
+    }
+
+    #[test]
+    fn requires_date_tags_in_driving_privileges_when_strict() {
+        let privileges = Cbor::Array(vec![Cbor::Map(vec![
+            (text("vehicle_category_code"), text("B")),
+            (text("issue_date"), text("2020-01-01")),
+        ])]);
+        assert!(element_to_json(MDL_NAMESPACE, "driving_privileges", &privileges, false).is_ok());
+        assert!(element_to_json(MDL_NAMESPACE, "driving_privileges", &privileges, true).is_err());
+    }
+}
@@ -0,0 +1,750 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::{Context, Result, bail};
+use base64::prelude::*;
+use isomdl::{
+    definitions::{validity_info::ValidityInfo, x509::trust_anchor::TrustAnchorRegistry},
+    presentation::authentication::{AuthenticationStatus, ResponseAuthenticationOutcome},
+};
+use pkcs8::der::Decode;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value, json};
+use sha2::{Digest, Sha256};
+use time::{OffsetDateTime, PrimitiveDateTime};
+use url::Url;
+use x509_cert::{
+    Certificate,
+    ext::pkix::{SubjectAltName, name::GeneralName},
+};
+
+use crate::{
+    disclosure::RequestedElements,
+    signature::{
+        SignatureEncoding, check_issuing_certificate, check_jwk_algorithm, check_jws_algorithm,
+        key_usage, verify_ecdsa, verify_ecdsa_jwk, verify_issued_by,
+    },
+    status_list::StatusListReference,
+    validity::check_certificate_validity,
+    verdict::PARSING_ERRORS,
+};
+
+/// The OpenID4VP credential format identifier of SD-JWT VCs.
+pub const SD_JWT_VC_FORMAT: &str = "dc+sd-jwt";
+
+/// Code of the [VerdictReason](crate::verdict::VerdictReason) reporting an
+/// SD-JWT VC of a type that was not requested.
+pub const VCT_NOT_ACCEPTED: &str = "vct_not_accepted";
+
+/// Keys of the [ResponseAuthenticationOutcome] errors reporting SD-JWT VC
+/// verification failures.
+pub use crate::verdict::{
+    DEVICE_AUTHENTICATION_ERRORS, ISSUER_AUTHENTICATION_ERRORS, VALIDITY_ERRORS,
+};
+
+/// How long after its creation a key binding JWT is accepted.
+const KEY_BINDING_MAX_AGE_SECONDS: i64 = 300;
+
+/// Claims of an SD-JWT VC that describe the credential rather than its subject,
+/// left out of the returned claims.
+const CREDENTIAL_CLAIMS: &[&str] = &[
+    "iss",
+    "nbf",
+    "exp",
+    "iat",
+    "cnf",
+    "vct",
+    "vct#integrity",
+    "status",
+];
+
+/// An SD-JWT VC requested over OpenID4VP, as an alternative to the mdoc.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SdJwtVcRequest {
+    /// Accepted `vct` values.
+    pub vct_values: Vec<String>,
+    /// Paths of the requested claims, e.g. `["address", "country"]`.
+    pub claims: Vec<Vec<String>>,
+}
+
+impl SdJwtVcRequest {
+    /// The requested top-level claims, under the returned `vct`.
+    pub fn requested_elements(&self, vct: &str) -> RequestedElements {
+        let claims = self
+            .claims
+            .iter()
+            .filter_map(|path| path.first().cloned())
+            .collect();
+        [(vct.to_string(), claims)].into_iter().collect()
+    }
+}
+
+/// What an SD-JWT VC presentation is verified against.
+pub struct SdJwtVcVerifier<'a> {
+    pub trust_anchor_registry: &'a TrustAnchorRegistry,
+    /// The nonce of the request, expected in the key binding JWT.
+    pub nonce: &'a str,
+    /// Accepted `aud` values of the key binding JWT.
+    pub audiences: &'a [String],
+    pub now: OffsetDateTime,
+    pub clock_skew_seconds: i64,
+}
+
+/// The issuer-signed data of a verified SD-JWT VC that policy checks apply to.
+pub struct SdJwtVc {
+    pub vct: String,
+    /// The `iat`, `nbf` and `exp` of the credential, checked against the
+    /// [ValidityPolicy](crate::validity::ValidityPolicy) as the `validityInfo`
+    /// of an MSO is.
+    pub validity_info: Option<ValidityInfo>,
+    /// The status list reference, or why the `status` could not be decoded.
+    pub status_list: Result<Option<StatusListReference>, String>,
+    /// The `x5c` of the issuer signature, starting with the signer.
+    pub x5c: Vec<Certificate>,
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+    #[serde(default)]
+    typ: Option<String>,
+    #[serde(default)]
+    x5c: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct KeyBindingClaims {
+    iat: i64,
+    aud: String,
+    nonce: String,
+    sd_hash: String,
+}
+
+#[derive(Deserialize)]
+struct Status {
+    #[serde(default)]
+    status_list: Option<StatusListReference>,
+}
+
+/// A decoded, not yet verified, compact JWS.
+struct Jws<'a> {
+    header: JwtHeader,
+    payload: Value,
+    message: &'a str,
+    signature: Vec<u8>,
+}
+
+enum Disclosure {
+    Property(String, Value),
+    ArrayElement(Value),
+}
+
+impl SdJwtVcVerifier<'_> {
+    /// Verifies a presentation, `<issuer-signed JWT>~<disclosure>~...~<KB-JWT>`.
+    ///
+    /// As for mdocs, failures are reported in the errors of the outcome, whose
+    /// `response` maps the `vct` to the disclosed claims.
+    pub fn verify(&self, presentation: &str) -> (ResponseAuthenticationOutcome, Option<SdJwtVc>) {
+        let mut outcome = ResponseAuthenticationOutcome::default();
+        let (issuer_jws, disclosures, key_binding) = match decode(presentation) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                outcome
+                    .errors
+                    .insert(PARSING_ERRORS.to_string(), json!(vec![format!("{e:#}")]));
+                return (outcome, None);
+            }
+        };
+        let claims = match resolve_disclosures(issuer_jws.payload.clone(), disclosures) {
+            Ok(claims) => claims,
+            Err(e) => {
+                outcome
+                    .errors
+                    .insert(PARSING_ERRORS.to_string(), json!(vec![format!("{e:#}")]));
+                return (outcome, None);
+            }
+        };
+        let Some(vct) = claims
+            .get("vct")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+        else {
+            outcome.errors.insert(
+                PARSING_ERRORS.to_string(),
+                json!(vec!["SD-JWT VC has no vct"]),
+            );
+            return (outcome, None);
+        };
+        let validity_info = match validity_info(&claims) {
+            Ok(validity_info) => validity_info,
+            Err(e) => {
+                outcome
+                    .errors
+                    .insert(PARSING_ERRORS.to_string(), json!(vec![format!("{e:#}")]));
+                return (outcome, None);
+            }
+        };
+
+        let x5c = match self.verify_issuer(&issuer_jws) {
+            Ok(x5c) => {
+                outcome.issuer_authentication = AuthenticationStatus::Valid;
+                x5c
+            }
+            Err(e) => {
+                outcome.issuer_authentication = AuthenticationStatus::Invalid;
+                outcome.errors.insert(
+                    ISSUER_AUTHENTICATION_ERRORS.to_string(),
+                    json!(vec![format!("{e:#}")]),
+                );
+                Vec::new()
+            }
+        };
+        match self.verify_key_binding(presentation, &key_binding, &claims) {
+            Ok(()) => outcome.device_authentication = AuthenticationStatus::Valid,
+            Err(e) => {
+                outcome.device_authentication = AuthenticationStatus::Invalid;
+                outcome.errors.insert(
+                    DEVICE_AUTHENTICATION_ERRORS.to_string(),
+                    json!(vec![format!("{e:#}")]),
+                );
+            }
+        }
+
+        let status_list = match claims.get("status") {
+            Some(status) => serde_json::from_value::<Status>(status.clone())
+                .map(|status| status.status_list)
+                .map_err(|e| format!("Could not decode status: {e}")),
+            None => Ok(None),
+        };
+        let subject_claims: Map<String, Value> = claims
+            .into_iter()
+            .filter(|(claim, _)| !CREDENTIAL_CLAIMS.contains(&claim.as_str()))
+            .collect();
+        outcome.response = BTreeMap::from([(vct.clone(), Value::Object(subject_claims))]);
+
+        (
+            outcome,
+            Some(SdJwtVc {
+                vct,
+                validity_info,
+                status_list,
+                x5c,
+            }),
+        )
+    }
+
+    /// Verifies the issuer signature with the `x5c` leaf, which the `iss` must
+    /// name, and the `x5c` up to a trust anchor.
+    fn verify_issuer(&self, jws: &Jws) -> Result<Vec<Certificate>> {
+        if !matches!(jws.header.typ.as_deref(), Some("dc+sd-jwt" | "vc+sd-jwt")) {
+            bail!("unexpected SD-JWT VC type {:?}", jws.header.typ);
+        }
+        let x5c = jws
+            .header
+            .x5c
+            .iter()
+            .map(|certificate| {
+                Certificate::from_der(
+                    &BASE64_STANDARD
+                        .decode(certificate)
+                        .context("Could not decode x5c")?,
+                )
+                .context("Could not decode x5c certificate")
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let signer = x5c.first().context("SD-JWT VC has no x5c")?;
+        check_jws_algorithm(
+            &jws.header.alg,
+            &signer.tbs_certificate.subject_public_key_info,
+        )?;
+        verify_ecdsa(
+            &signer.tbs_certificate.subject_public_key_info,
+            jws.message.as_bytes(),
+            &jws.signature,
+            SignatureEncoding::Fixed,
+        )
+        .context("invalid issuer signature")?;
+        let iss = jws
+            .payload
+            .get("iss")
+            .and_then(Value::as_str)
+            .context("SD-JWT VC has no iss")?;
+        check_issuer_name(iss, signer)?;
+        self.verify_x5c(&x5c)?;
+        Ok(x5c)
+    }
+
+    /// Verifies the `x5c` up to a trust anchor. Every certificate, and the
+    /// anchor, must be valid, the leaf must be allowed to sign, and the
+    /// certificates issuing the others must be CAs allowed to sign certificates.
+    fn verify_x5c(&self, x5c: &[Certificate]) -> Result<()> {
+        let leaf = x5c.first().context("SD-JWT VC has no x5c")?;
+        if !key_usage(leaf)?.is_some_and(|usage| usage.digital_signature()) {
+            bail!(
+                "x5c leaf {} does not allow digitalSignature",
+                leaf.tbs_certificate.subject
+            );
+        }
+        for certificate in x5c {
+            check_certificate_validity(certificate, self.now)?;
+        }
+        for pair in x5c.windows(2) {
+            check_issuing_certificate(&pair[1])?;
+            verify_issued_by(&pair[0], &pair[1])?;
+        }
+        let last = x5c.last().context("SD-JWT VC has no x5c")?;
+        let anchor = self
+            .trust_anchor_registry
+            .anchors
+            .iter()
+            .map(|anchor| &anchor.certificate)
+            .find(|anchor| *anchor == last || verify_issued_by(last, anchor).is_ok())
+            .with_context(|| {
+                format!(
+                    "issuer {} does not chain to a trust anchor",
+                    last.tbs_certificate.issuer
+                )
+            })?;
+        check_certificate_validity(anchor, self.now)?;
+        if anchor != last {
+            check_issuing_certificate(anchor)?;
+        }
+        Ok(())
+    }
+
+    /// Verifies the key binding JWT against the `cnf` key of the credential,
+    /// the request nonce and audience, and the presented disclosures.
+    fn verify_key_binding(
+        &self,
+        presentation: &str,
+        jws: &Jws,
+        claims: &Map<String, Value>,
+    ) -> Result<()> {
+        if jws.header.typ.as_deref() != Some("kb+jwt") {
+            bail!("unexpected key binding JWT type {:?}", jws.header.typ);
+        }
+        let jwk = claims
+            .get("cnf")
+            .and_then(|cnf| cnf.get("jwk"))
+            .context("SD-JWT VC has no cnf key")?;
+        check_jwk_algorithm(&jws.header.alg, jwk)?;
+        verify_ecdsa_jwk(jwk, jws.message.as_bytes(), &jws.signature)
+            .context("invalid key binding signature")?;
+
+        let kb: KeyBindingClaims = serde_json::from_value(jws.payload.clone())
+            .context("Could not deserialize key binding JWT claims")?;
+        if kb.nonce != self.nonce {
+            bail!("key binding nonce does not match the request");
+        }
+        if !self.audiences.contains(&kb.aud) {
+            bail!("unexpected key binding audience {}", kb.aud);
+        }
+        let now = self.now.unix_timestamp();
+        if kb.iat > now.saturating_add(self.clock_skew_seconds)
+            || kb.iat
+                < now
+                    .saturating_sub(KEY_BINDING_MAX_AGE_SECONDS)
+                    .saturating_sub(self.clock_skew_seconds)
+        {
+            bail!("key binding JWT issued at {} is not recent", kb.iat);
+        }
+        // The hash covers the issuer-signed JWT and disclosures, up to the last `~`.
+        let presented = presentation
+            .rfind('~')
+            .map(|end| &presentation[..=end])
+            .context("SD-JWT has no key binding JWT")?;
+        if BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(presented)) != kb.sd_hash {
+            bail!("key binding sd_hash does not match the presentation");
+        }
+        Ok(())
+    }
+}
+
+/// The `iat`, `nbf` and `exp` NumericDates of the credential as a
+/// [ValidityInfo], or `None` if it has none of them.
+///
+/// A missing `nbf` defaults to `iat`, and a missing `exp` never expires. A
+/// credential without `iat` or `nbf` has no known age, which fails a maximum
+/// credential age.
+fn validity_info(claims: &Map<String, Value>) -> Result<Option<ValidityInfo>> {
+    let numeric_date = |claim: &str| {
+        claims
+            .get(claim)
+            .map(|value| {
+                let seconds = value
+                    .as_i64()
+                    .with_context(|| format!("{claim} must be an integer NumericDate"))?;
+                OffsetDateTime::from_unix_timestamp(seconds)
+                    .with_context(|| format!("{claim} is out of range"))
+            })
+            .transpose()
+    };
+    let (iat, nbf, exp) = (
+        numeric_date("iat")?,
+        numeric_date("nbf")?,
+        numeric_date("exp")?,
+    );
+    if iat.is_none() && nbf.is_none() && exp.is_none() {
+        return Ok(None);
+    }
+    let valid_from = nbf.or(iat);
+    Ok(Some(ValidityInfo {
+        signed: iat
+            .or(valid_from)
+            .unwrap_or(PrimitiveDateTime::MIN.assume_utc()),
+        valid_from: valid_from.unwrap_or(PrimitiveDateTime::MIN.assume_utc()),
+        valid_until: exp.unwrap_or(PrimitiveDateTime::MAX.assume_utc()),
+        expected_update: None,
+    }))
+}
+
+/// Checks that `iss` names the `x5c` leaf: it must be one of its URI
+/// subject alternative names, or an `https` URL whose host is one of its DNS
+/// names.
+fn check_issuer_name(iss: &str, leaf: &Certificate) -> Result<()> {
+    let names = leaf
+        .tbs_certificate
+        .get::<SubjectAltName>()
+        .context("Could not decode subject alternative names")?
+        .map(|(_, names)| names.0)
+        .unwrap_or_default();
+    let host = Url::parse(iss)
+        .ok()
+        .filter(|url| url.scheme() == "https")
+        .and_then(|url| url.host_str().map(str::to_string));
+    let named = names.iter().any(|name| match name {
+        GeneralName::UniformResourceIdentifier(uri) => uri.as_str() == iss,
+        GeneralName::DnsName(dns) => host
+            .as_deref()
+            .is_some_and(|host| dns.as_str().eq_ignore_ascii_case(host)),
+        _ => false,
+    });
+    if !named {
+        bail!("iss {iss} is not a subject alternative name of the x5c leaf");
+    }
+    Ok(())
+}
+
+/// Splits a presentation into its issuer-signed JWT, disclosures and key
+/// binding JWT.
+fn decode(presentation: &str) -> Result<(Jws<'_>, HashMap<String, Disclosure>, Jws<'_>)> {
+    let parts: Vec<&str> = presentation.split('~').collect();
+    let (Some(&issuer_jwt), Some(&key_binding_jwt)) = (parts.first(), parts.last()) else {
+        bail!("vp_token is not an SD-JWT");
+    };
+    if parts.len() < 2 || key_binding_jwt.is_empty() {
+        bail!("SD-JWT has no key binding JWT");
+    }
+    let issuer_jws = decode_jws(issuer_jwt).context("Could not decode issuer-signed JWT")?;
+    let sd_alg = issuer_jws
+        .payload
+        .get("_sd_alg")
+        .and_then(Value::as_str)
+        .unwrap_or("sha-256");
+    if sd_alg != "sha-256" {
+        bail!("unsupported _sd_alg {sd_alg}");
+    }
+
+    let mut disclosures = HashMap::new();
+    for disclosure in &parts[1..parts.len() - 1] {
+        let digest = BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(disclosure));
+        let decoded: Vec<Value> = decode_part(disclosure).context("Could not decode disclosure")?;
+        let disclosure = match <[Value; 3]>::try_from(decoded) {
+            Ok([_, Value::String(name), value]) => Disclosure::Property(name, value),
+            Ok(_) => bail!("disclosure claim name must be a string"),
+            Err(decoded) => match <[Value; 2]>::try_from(decoded) {
+                Ok([_, value]) => Disclosure::ArrayElement(value),
+                Err(_) => bail!("disclosure must have 2 or 3 elements"),
+            },
+        };
+        if disclosures.insert(digest, disclosure).is_some() {
+            bail!("duplicate disclosure");
+        }
+    }
+    let key_binding_jws =
+        decode_jws(key_binding_jwt).context("Could not decode key binding JWT")?;
+
+    Ok((issuer_jws, disclosures, key_binding_jws))
+}
+
+fn decode_jws(jwt: &str) -> Result<Jws<'_>> {
+    let mut parts = jwt.split('.');
+    let (Some(header), Some(payload), Some(signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        bail!("not a compact JWS");
+    };
+    Ok(Jws {
+        header: decode_part(header).context("Could not decode JWT header")?,
+        payload: decode_part(payload).context("Could not decode JWT payload")?,
+        message: &jwt[..jwt.len() - signature.len() - 1],
+        signature: BASE64_URL_SAFE_NO_PAD
+            .decode(signature)
+            .context("Could not decode JWT signature")?,
+    })
+}
+
+fn decode_part<T: DeserializeOwned>(part: &str) -> Result<T> {
+    let bytes = BASE64_URL_SAFE_NO_PAD
+        .decode(part)
+        .context("invalid base64url")?;
+    serde_json::from_slice(&bytes).context("invalid JSON")
+}
+
+/// Replaces the digests of the issuer-signed `payload` with the disclosed
+/// claims. Every disclosure must be referenced exactly once.
+fn resolve_disclosures(
+    payload: Value,
+    disclosures: HashMap<String, Disclosure>,
+) -> Result<Map<String, Value>> {
+    let mut resolver = Resolver {
+        disclosures,
+        seen: HashSet::new(),
+    };
+    let Value::Object(claims) = resolver.resolve(payload)? else {
+        bail!("SD-JWT payload must be an object");
+    };
+    if !resolver.disclosures.is_empty() {
+        bail!("disclosures not referenced by the credential");
+    }
+    Ok(claims)
+}
+
+struct Resolver {
+    disclosures: HashMap<String, Disclosure>,
+    seen: HashSet<String>,
+}
+
+impl Resolver {
+    fn resolve(&mut self, value: Value) -> Result<Value> {
+        match value {
+            Value::Object(mut object) => {
+                let digests = match object.remove("_sd") {
+                    None => Vec::new(),
+                    Some(Value::Array(digests)) => digests,
+                    Some(_) => bail!("_sd must be an array"),
+                };
+                object.remove("_sd_alg");
+                let mut resolved = Map::new();
+                for (name, value) in object {
+                    resolved.insert(name, self.resolve(value)?);
+                }
+                for digest in digests {
+                    let digest = digest.as_str().context("_sd digests must be strings")?;
+                    match self.take(digest)? {
+                        Some(Disclosure::Property(name, value)) => {
+                            if name == "_sd" || name == "..." || resolved.contains_key(&name) {
+                                bail!("invalid disclosed claim name {name}");
+                            }
+                            let value = self.resolve(value)?;
+                            resolved.insert(name, value);
+                        }
+                        Some(Disclosure::ArrayElement(_)) => {
+                            bail!("array element disclosure referenced from _sd")
+                        }
+                        None => {}
+                    }
+                }
+                Ok(Value::Object(resolved))
+            }
+            Value::Array(values) => {
+                let mut resolved = Vec::new();
+                for value in values {
+                    let digest = match &value {
+                        Value::Object(object) if object.len() == 1 => object
+                            .get("...")
+                            .and_then(Value::as_str)
+                            .map(str::to_string),
+                        _ => None,
+                    };
+                    let Some(digest) = digest else {
+                        resolved.push(self.resolve(value)?);
+                        continue;
+                    };
+                    match self.take(&digest)? {
+                        Some(Disclosure::ArrayElement(value)) => {
+                            let value = self.resolve(value)?;
+                            resolved.push(value);
+                        }
+                        Some(Disclosure::Property(..)) => {
+                            bail!("claim disclosure referenced from an array")
+                        }
+                        None => {}
+                    }
+                }
+                Ok(Value::Array(resolved))
+            }
+            value => Ok(value),
+        }
+    }
+
+    /// Takes the disclosure of `digest`, if presented. Digests must be unique.
+    fn take(&mut self, digest: &str) -> Result<Option<Disclosure>> {
+        if !self.seen.insert(digest.to_string()) {
+            bail!("duplicate digest {digest}");
+        }
+        Ok(self.disclosures.remove(digest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        AUD, CertificateSpec, ISS, NONCE, NOW, TestChain, VCT, digest, disclosure, jwt, key,
+        sd_jwt_presentation,
+    };
+
+    fn verify(
+        chain: &TestChain,
+        presentation: &str,
+    ) -> (ResponseAuthenticationOutcome, Option<SdJwtVc>) {
+        SdJwtVcVerifier {
+            trust_anchor_registry: &chain.registry(),
+            nonce: NONCE,
+            audiences: &[AUD.to_string()],
+            now: OffsetDateTime::from_unix_timestamp(NOW).unwrap(),
+            clock_skew_seconds: 300,
+        }
+        .verify(presentation)
+    }
+
+    /// Presents the credential issued with `claims`, without disclosures.
+    fn present(chain: &TestChain, alg: &str, claims: Value) -> String {
+        let sd_jwt = chain.sd_jwt(alg, &[&chain.ds], claims);
+        sd_jwt_presentation(&sd_jwt, &[], &chain.holder, NONCE, AUD)
+    }
+
+    fn error_keys(outcome: &ResponseAuthenticationOutcome) -> Vec<&str> {
+        outcome.errors.keys().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn verifies_presentation() {
+        let chain = TestChain::new();
+        let (outcome, verified) = verify(
+            &chain,
+            &chain.present(&[disclosure("given_name", json!("Erika"))]),
+        );
+        assert!(outcome.errors.is_empty(), "{:?}", outcome.errors);
+        assert!(matches!(
+            outcome.issuer_authentication,
+            AuthenticationStatus::Valid
+        ));
+        assert!(matches!(
+            outcome.device_authentication,
+            AuthenticationStatus::Valid
+        ));
+        assert_eq!(outcome.response[VCT]["given_name"], "Erika");
+        let validity_info = verified.unwrap().validity_info.unwrap();
+        assert_eq!(validity_info.signed.unix_timestamp(), NOW);
+        assert_eq!(validity_info.valid_until.unix_timestamp(), NOW + 86_400);
+    }
+
+    #[test]
+    fn rejects_tampered_disclosure() {
+        let chain = TestChain::new();
+        let claims = chain.sd_jwt_claims(&[disclosure("given_name", json!("Erika"))]);
+        let sd_jwt = chain.sd_jwt("ES256", &[&chain.ds], claims);
+        let tampered = [disclosure("given_name", json!("Max"))];
+        let presentation = sd_jwt_presentation(&sd_jwt, &tampered, &chain.holder, NONCE, AUD);
+        let (outcome, verified) = verify(&chain, &presentation);
+        assert_eq!(error_keys(&outcome), [PARSING_ERRORS]);
+        assert!(verified.is_none());
+    }
+
+    #[test]
+    fn rejects_unreferenced_disclosure() {
+        let chain = TestChain::new();
+        let given_name = disclosure("given_name", json!("Erika"));
+        let claims = chain.sd_jwt_claims(std::slice::from_ref(&given_name));
+        let sd_jwt = chain.sd_jwt("ES256", &[&chain.ds], claims);
+        let disclosures = [given_name, disclosure("family_name", json!("Mustermann"))];
+        let presentation = sd_jwt_presentation(&sd_jwt, &disclosures, &chain.holder, NONCE, AUD);
+        let (outcome, _) = verify(&chain, &presentation);
+        assert_eq!(error_keys(&outcome), [PARSING_ERRORS]);
+    }
+
+    #[test]
+    fn rejects_key_binding_to_another_nonce_or_audience() {
+        let chain = TestChain::new();
+        let sd_jwt = chain.sd_jwt("ES256", &[&chain.ds], chain.sd_jwt_claims(&[]));
+        for (nonce, aud) in [("other", AUD), (NONCE, "x509_san_dns:other.example")] {
+            let presentation = sd_jwt_presentation(&sd_jwt, &[], &chain.holder, nonce, aud);
+            let (outcome, _) = verify(&chain, &presentation);
+            assert_eq!(error_keys(&outcome), [DEVICE_AUTHENTICATION_ERRORS]);
+            assert!(matches!(
+                outcome.device_authentication,
+                AuthenticationStatus::Invalid
+            ));
+            assert!(matches!(
+                outcome.issuer_authentication,
+                AuthenticationStatus::Valid
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_key_binding_with_algorithm_not_matching_key() {
+        let chain = TestChain::new();
+        let sd_jwt = chain.sd_jwt("ES256", &[&chain.ds], chain.sd_jwt_claims(&[]));
+        let presented = format!("{sd_jwt}~");
+        let key_binding = jwt(
+            &chain.holder,
+            json!({ "alg": "ES384", "typ": "kb+jwt" }),
+            json!({ "iat": NOW, "aud": AUD, "nonce": NONCE, "sd_hash": digest(&presented) }),
+        );
+        let (outcome, _) = verify(&chain, &format!("{presented}{key_binding}"));
+        assert_eq!(error_keys(&outcome), [DEVICE_AUTHENTICATION_ERRORS]);
+    }
+
+    #[test]
+    fn rejects_issuer_signature_with_algorithm_not_matching_key() {
+        let chain = TestChain::new();
+        let (outcome, _) = verify(&chain, &present(&chain, "ES384", chain.sd_jwt_claims(&[])));
+        assert_eq!(error_keys(&outcome), [ISSUER_AUTHENTICATION_ERRORS]);
+    }
+
+    #[test]
+    fn rejects_iss_not_named_by_signer() {
+        let chain = TestChain::new();
+        let mut claims = chain.sd_jwt_claims(&[]);
+        claims["iss"] = json!("https://other.example");
+        let (outcome, _) = verify(&chain, &present(&chain, "ES256", claims));
+        assert_eq!(error_keys(&outcome), [ISSUER_AUTHENTICATION_ERRORS]);
+    }
+
+    #[test]
+    fn rejects_x5c_issued_by_non_ca() {
+        let mut chain = TestChain::new();
+        let intermediate = CertificateSpec::leaf("CN=Not a CA").issue(key(4), Some(&chain.iaca));
+        chain.ds = CertificateSpec {
+            san_uri: Some(ISS),
+            ..CertificateSpec::leaf("CN=Test DS")
+        }
+        .issue(key(2), Some(&intermediate));
+        let sd_jwt = chain.sd_jwt(
+            "ES256",
+            &[&chain.ds, &intermediate],
+            chain.sd_jwt_claims(&[]),
+        );
+        let presentation = sd_jwt_presentation(&sd_jwt, &[], &chain.holder, NONCE, AUD);
+        let (outcome, _) = verify(&chain, &presentation);
+        assert_eq!(error_keys(&outcome), [ISSUER_AUTHENTICATION_ERRORS]);
+        assert!(
+            outcome.errors[ISSUER_AUTHENTICATION_ERRORS]
+                .to_string()
+                .contains("not a CA")
+        );
+    }
+
+    #[test]
+    fn rejects_non_integer_numeric_dates() {
+        let chain = TestChain::new();
+        for exp in [json!(1.5), json!("1767225600")] {
+            let mut claims = chain.sd_jwt_claims(&[]);
+            claims["exp"] = exp;
+            let (outcome, _) = verify(&chain, &present(&chain, "ES256", claims));
+            assert_eq!(error_keys(&outcome), [PARSING_ERRORS]);
+        }
+    }
+}
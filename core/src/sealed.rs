@@ -0,0 +1,280 @@
+use std::sync::Arc;
+
+use aes_gcm::{
+    Aes256Gcm, Nonce,
+    aead::{Aead, KeyInit, Payload},
+};
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use base64::prelude::*;
+use isomdl::cbor;
+use openid4vp::verifier::session::{Session, SessionStore, Status};
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::{
+    annex_c, annex_d,
+    clock::{Clock, SystemClock},
+    error::DcApiError,
+};
+
+/// Associated data bound to every sealed token, so that tokens cannot be
+/// confused with other ciphertexts produced under the same key.
+const SEALED_TOKEN_AAD: &[u8] = b"dc-api-sealed-session-v1";
+const SEALED_TOKEN_NONCE_LEN: usize = 12;
+const SEALED_TOKEN_ID_LEN: usize = 16;
+const DEFAULT_SEALED_TOKEN_TTL: Duration = Duration::minutes(5);
+
+/// Replay protection for sealed tokens.
+///
+/// Implementations only need to remember a token id until its expiry, after
+/// which the token is rejected by [SessionSealer::unseal] anyway.
+#[cfg_attr(target_arch="wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait NonceCache {
+    /// Records `token_id` as used until `expires_at` (unix timestamp in seconds).
+    ///
+    /// Returns `false` if the token id was already recorded.
+    async fn consume(&self, token_id: String, expires_at: i64) -> Result<bool>;
+}
+
+/// The `Initiated` session state, as carried inside a sealed token.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SealedSessionState {
+    token_id: String,
+    expires_at: i64,
+    pub annex_c: annex_c::InitiatedSessionState,
+    pub annex_d: annex_d::InitiatedSessionState,
+}
+
+/// Seals initiated session states into authenticated encrypted tokens
+/// (AES-256-GCM), so that no server-side session storage is required
+/// between initiating a request and submitting the response.
+#[derive(Clone)]
+pub struct SessionSealer {
+    cipher: Aes256Gcm,
+    ttl: Duration,
+}
+
+impl SessionSealer {
+    /// `key` must be 32 bytes long.
+    pub fn new(key: &[u8]) -> Result<Self> {
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|_| DcApiError::InvalidRequest("sealing key must be 32 bytes long".into()))?;
+        Ok(Self {
+            cipher,
+            ttl: DEFAULT_SEALED_TOKEN_TTL,
+        })
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn seal(
+        &self,
+        annex_c: annex_c::InitiatedSessionState,
+        annex_d: annex_d::InitiatedSessionState,
+        now: OffsetDateTime,
+    ) -> Result<String> {
+        let mut token_id = [0u8; SEALED_TOKEN_ID_LEN];
+        getrandom::fill(&mut token_id).expect("Failed to generate token id");
+        let state = SealedSessionState {
+            token_id: BASE64_URL_SAFE_NO_PAD.encode(token_id),
+            expires_at: (now + self.ttl).unix_timestamp(),
+            annex_c,
+            annex_d,
+        };
+        let plaintext = cbor::to_vec(&state).context("Could not serialize sealed state")?;
+
+        let mut nonce = [0u8; SEALED_TOKEN_NONCE_LEN];
+        getrandom::fill(&mut nonce).expect("Failed to generate nonce");
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: SEALED_TOKEN_AAD,
+                },
+            )
+            .map_err(|_| anyhow!("Could not seal session state"))?;
+
+        Ok(BASE64_URL_SAFE_NO_PAD.encode([nonce.as_slice(), &ciphertext].concat()))
+    }
+
+    /// Decrypts and authenticates a token, rejecting it if it has expired.
+    ///
+    /// This does not provide replay protection, see [SealedSessions::open].
+    pub fn unseal(&self, token: &str, now: OffsetDateTime) -> Result<SealedSessionState> {
+        let token = BASE64_URL_SAFE_NO_PAD.decode(token).map_err(|e| {
+            DcApiError::InvalidSealedToken(format!("Could not decode sealed token: {e}"))
+        })?;
+        if token.len() <= SEALED_TOKEN_NONCE_LEN {
+            bail!(DcApiError::InvalidSealedToken(
+                "sealed token is too short".to_string()
+            ));
+        }
+        let (nonce, ciphertext) = token.split_at(SEALED_TOKEN_NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: SEALED_TOKEN_AAD,
+                },
+            )
+            .map_err(|_| {
+                DcApiError::InvalidSealedToken("sealed token failed authentication".to_string())
+            })?;
+        let state: SealedSessionState =
+            cbor::from_slice(&plaintext).context("Could not deserialize sealed state")?;
+
+        if state.expires_at < now.unix_timestamp() {
+            bail!(DcApiError::InvalidSealedToken(
+                "sealed token has expired".to_string()
+            ));
+        }
+
+        Ok(state)
+    }
+}
+
+/// Stateless sessions: a [SessionSealer] paired with a [NonceCache] so that
+/// each sealed token can only be submitted once.
+#[derive(Clone)]
+pub struct SealedSessions {
+    sealer: SessionSealer,
+    nonce_cache: Arc<dyn NonceCache>,
+    clock: Arc<dyn Clock>,
+}
+
+impl SealedSessions {
+    pub fn new(sealer: SessionSealer, nonce_cache: Arc<dyn NonceCache>) -> Self {
+        Self {
+            sealer,
+            nonce_cache,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Replaces the [SystemClock] used for token expiry.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    pub fn seal(
+        &self,
+        annex_c: annex_c::InitiatedSessionState,
+        annex_d: annex_d::InitiatedSessionState,
+    ) -> Result<String> {
+        self.sealer.seal(annex_c, annex_d, self.clock.now())
+    }
+
+    /// Unseals a token, rejecting it if it has expired.
+    ///
+    /// The token is not marked as used until [SealedSessions::consume], so that
+    /// a malformed or unverifiable submission does not burn it.
+    pub fn open(&self, token: &str) -> Result<SealedSessionState> {
+        self.sealer.unseal(token, self.clock.now())
+    }
+
+    /// Marks the token of `state` as used, once its response has been verified.
+    ///
+    /// Fails if the token has already been used: a replayed token is rejected
+    /// after verification, without its result being returned.
+    pub async fn consume(&self, state: &SealedSessionState) -> Result<()> {
+        if !self
+            .nonce_cache
+            .consume(state.token_id.clone(), state.expires_at)
+            .await?
+        {
+            bail!(DcApiError::InvalidSealedToken(
+                "sealed token has already been used".to_string()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// An OID4VP [SessionStore] that keeps nothing, for the OID4VP verifier of the
+/// sealed flow: sealed requests then leave no state behind in any store.
+///
+/// The DC API flow never reads OID4VP sessions back, their status is only
+/// kept for the caller's records.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SealedOid4VpSessionStore;
+
+#[cfg_attr(target_arch="wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl SessionStore for SealedOid4VpSessionStore {
+    async fn initiate(&self, _session: Session) -> Result<()> {
+        Ok(())
+    }
+
+    async fn update_status(&self, _uuid: Uuid, _status: Status) -> Result<()> {
+        Ok(())
+    }
+
+    async fn get_session(&self, uuid: Uuid) -> Result<Session> {
+        bail!("OID4VP session {uuid} of a sealed request is not stored")
+    }
+
+    async fn remove_session(&self, _uuid: Uuid) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, sync::Mutex};
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MemoryNonceCache(Mutex<HashSet<String>>);
+
+    #[async_trait]
+    impl NonceCache for MemoryNonceCache {
+        async fn consume(&self, token_id: String, _expires_at: i64) -> Result<bool> {
+            Ok(self.0.lock().unwrap().insert(token_id))
+        }
+    }
+
+    #[tokio::test]
+    async fn only_consumes_tokens_once_verified() {
+        let sessions = SealedSessions::new(
+            SessionSealer::new(&[7; 32]).unwrap(),
+            Arc::new(MemoryNonceCache::default()),
+        );
+        let annex_c = serde_json::from_value(json!({
+            "private_key": [],
+            "session_transcript_bytes": [],
+        }))
+        .unwrap();
+        let annex_d = serde_json::from_value(json!({
+            "oid4vp_session_id": "session",
+            "origin": "https://rp.example.com",
+            "nonce": "nonce",
+        }))
+        .unwrap();
+        let token = sessions.seal(annex_c, annex_d).unwrap();
+
+        // Opening a token, e.g. for a submission that fails, does not use it up.
+        sessions.open(&token).unwrap();
+        let state = sessions.open(&token).unwrap();
+
+        sessions.consume(&state).await.unwrap();
+        let replayed = sessions.open(&token).unwrap();
+        let error = sessions.consume(&replayed).await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(DcApiError::InvalidSealedToken(_))
+        ));
+    }
+}
@@ -3,11 +3,28 @@ use std::sync::Arc;
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use base64::{Engine, prelude::BASE64_STANDARD};
-use isomdl::presentation::authentication::ResponseAuthenticationOutcome;
+use hmac::{Hmac, Mac};
+use openid4vp::verifier::session::{SessionStore, Status};
+use openid4vp_frontend::Outcome;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha512};
+use sha2::{Digest, Sha256, Sha512};
+use subtle::ConstantTimeEq;
+use time::{Duration, OffsetDateTime};
 use uuid::Uuid;
 
+use crate::{
+    clock::{Clock, SystemClock},
+    error::DcApiError,
+    events::{EventSpawner, SessionEvent, SessionEventHook, SessionEventKind},
+    verdict::VerificationResult,
+};
+
+type HmacSha512 = Hmac<Sha512>;
+
+const DEFAULT_MAX_INITIATION_ATTEMPTS: u32 = 3;
+const DEFAULT_MAX_FAILED_SUBMISSIONS: u32 = 5;
+const DEFAULT_MAX_SESSION_AGE: Duration = Duration::days(1);
+
 #[cfg_attr(target_arch="wasm32", async_trait(?Send))]
 #[cfg_attr(not(target_arch = "wasm32"), async_trait)]
 pub trait DcApiSessionEngine {
@@ -16,12 +33,89 @@ pub trait DcApiSessionEngine {
     async fn get_session_unauthenticated(&self, id: String) -> Result<Option<Session>>;
     async fn update_session(&self, session_id: String, session: Session) -> Result<()>;
     async fn remove_session(&self, session_id: String) -> Result<()>;
+    /// Lists the ids of all stored sessions, used by [SessionStorage::sweep].
+    ///
+    /// Engines that do not implement it cannot be swept.
+    async fn list_session_ids(&self) -> Result<Vec<String>> {
+        Err(DcApiError::NotConfigured("the session engine cannot list sessions".to_string()).into())
+    }
 }
 
+/// A shared [DcApiSessionEngine], which must be thread-safe outside of WebAssembly.
+#[cfg(not(target_arch = "wasm32"))]
+pub type SharedSessionEngine = Arc<dyn DcApiSessionEngine + Send + Sync>;
+#[cfg(target_arch = "wasm32")]
+pub type SharedSessionEngine = Arc<dyn DcApiSessionEngine>;
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Session {
     pub client_secret_hash: String,
     pub state: SessionState,
+    /// Unix timestamp in seconds at which the session was created.
+    #[serde(default)]
+    pub created_at: Option<i64>,
+    /// Unix timestamp in seconds after which a session that was not completed expires.
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    /// Unix timestamp in seconds at which the session reached a terminal state.
+    #[serde(default)]
+    pub completed_at: Option<i64>,
+    /// The linked `openid4vp` session, removed along with this session.
+    #[serde(default)]
+    pub oid4vp_session_id: Option<String>,
+    /// When set, the session can only be used from the same origin and client.
+    #[serde(default)]
+    pub binding: Option<SessionBinding>,
+    /// Number of times a request was initiated for this session.
+    #[serde(default)]
+    pub initiation_attempts: u32,
+    /// Number of submissions that failed for this session.
+    #[serde(default)]
+    pub failed_submission_attempts: u32,
+}
+
+/// Binds a session to the origin it was created for and, optionally, to a
+/// caller-supplied value identifying the client (e.g. a cookie), to defeat
+/// session fixation across relying-party pages.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionBinding {
+    pub origin: String,
+    /// Base64 encoded SHA-256 hash of the client binding value.
+    client_binding_hash: Option<String>,
+}
+
+impl SessionBinding {
+    pub fn new(origin: String, client_binding: Option<&str>) -> Self {
+        Self {
+            origin,
+            client_binding_hash: client_binding
+                .map(|value| BASE64_STANDARD.encode(Sha256::digest(value.as_bytes()))),
+        }
+    }
+
+    /// Checks that the presented origin and client binding value match the binding.
+    pub fn check(&self, origin: &str, client_binding: Option<&str>) -> Result<(), DcApiError> {
+        if self.origin != origin {
+            return Err(DcApiError::SessionBindingMismatch(
+                "origin mismatch".to_string(),
+            ));
+        }
+        if let Some(client_binding_hash) = &self.client_binding_hash {
+            let presented_hash = client_binding
+                .map(|value| BASE64_STANDARD.encode(Sha256::digest(value.as_bytes())))
+                .unwrap_or_default();
+            if !bool::from(
+                client_binding_hash
+                    .as_bytes()
+                    .ct_eq(presented_hash.as_bytes()),
+            ) {
+                return Err(DcApiError::SessionBindingMismatch(
+                    "client binding mismatch".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -31,10 +125,14 @@ pub struct SessionCreation {
 }
 
 impl Session {
-    pub fn new_with_creation_response() -> Result<SessionCreation> {
+    /// Creates a new session and its client secret.
+    ///
+    /// When a `pepper` is provided, the stored hash is an HMAC-SHA512 keyed with
+    /// it, so that a leaked session store does not allow verifying secrets offline.
+    pub fn new_with_creation_response(pepper: Option<&[u8]>) -> Result<SessionCreation> {
         let session_id = Uuid::new_v4().to_string();
-        let client_secret = Uuid::new_v4().to_string();
-        let client_secret_hash = Sha512::digest(client_secret.as_bytes());
+        let client_secret = crate::rng::random_secret();
+        let client_secret_hash = hash_client_secret(&client_secret, pepper);
         let session_creation_response = SessionCreationResponse {
             id: session_id.clone(),
             client_secret,
@@ -42,6 +140,13 @@ impl Session {
         let session = Session {
             client_secret_hash: BASE64_STANDARD.encode(client_secret_hash),
             state: SessionState::Created,
+            created_at: None,
+            expires_at: None,
+            completed_at: None,
+            oid4vp_session_id: None,
+            binding: None,
+            initiation_attempts: 0,
+            failed_submission_attempts: 0,
         };
 
         Ok(SessionCreation {
@@ -50,88 +155,566 @@ impl Session {
         })
     }
 
-    pub fn check_client_secret(client_secret: &str, hash: &str) -> Result<()> {
-        let client_secret_hash = Sha512::digest(client_secret.as_bytes());
+    pub fn check_client_secret(
+        client_secret: &str,
+        hash: &str,
+        pepper: Option<&[u8]>,
+    ) -> Result<()> {
+        let client_secret_hash = hash_client_secret(client_secret, pepper);
         let session_client_secret_hash = BASE64_STANDARD
             .decode(hash)
             .map_err(|e| anyhow!("client secret check failed: {e:?}"))?;
 
-        if session_client_secret_hash != client_secret_hash.to_vec() {
+        if !bool::from(session_client_secret_hash.ct_eq(&client_secret_hash)) {
             return Err(anyhow!("client secret mismatch"));
         }
 
         Ok(())
     }
+
+    /// A coarse view of the session state, which never includes the outcome.
+    pub fn status(&self, now: OffsetDateTime) -> SessionStatus {
+        if self.is_expired(now) {
+            return SessionStatus::Expired;
+        }
+        match self.state {
+            SessionState::Created => SessionStatus::Created,
+            SessionState::Initiated { .. } => SessionStatus::Initiated,
+            SessionState::Completed(_) => SessionStatus::Completed,
+            SessionState::Failed(_) => SessionStatus::Failed,
+            SessionState::Expired => SessionStatus::Expired,
+            SessionState::Locked => SessionStatus::Locked,
+        }
+    }
+
+    pub fn counters(&self) -> SessionCounters {
+        SessionCounters {
+            initiation_attempts: self.initiation_attempts,
+            failed_submission_attempts: self.failed_submission_attempts,
+            locked: self.state == SessionState::Locked,
+        }
+    }
+
+    /// Counts a failed submission, moving the session to [SessionState::Locked]
+    /// once `max_failures` is reached. Until then the session keeps its state,
+    /// so that the wallet can submit again. Completed and expired sessions are
+    /// counted but never locked, which would discard their outcome.
+    ///
+    /// Returns whether the session is now locked.
+    pub fn record_failed_submission(&mut self, max_failures: u32) -> bool {
+        self.failed_submission_attempts = self.failed_submission_attempts.saturating_add(1);
+        if self.failed_submission_attempts >= max_failures
+            && !matches!(
+                self.state,
+                SessionState::Completed(_) | SessionState::Expired | SessionState::Locked
+            )
+        {
+            self.state = SessionState::Locked;
+            return true;
+        }
+        false
+    }
+
+    /// Checks that a request can be (re-)initiated for this session and counts the attempt.
+    ///
+    /// `Initiated` and `Failed` sessions can be re-initiated, e.g. after the user
+    /// dismissed the wallet prompt, up to `max_attempts` initiations in total.
+    /// The caller replaces the state with freshly initiated annex states, which
+    /// invalidates the previous keys and nonce.
+    pub fn begin_initiation(&mut self, max_attempts: u32) -> Result<(), DcApiError> {
+        match self.state {
+            SessionState::Created => {}
+            SessionState::Initiated { .. } | SessionState::Failed(_) => {
+                if self.initiation_attempts >= max_attempts {
+                    return Err(DcApiError::TooManyAttempts(
+                        "too many initiation attempts".to_string(),
+                    ));
+                }
+            }
+            SessionState::Locked => return Err(DcApiError::SessionLocked),
+            _ => {
+                return Err(DcApiError::InvalidSessionState(
+                    "session cannot be initiated".to_string(),
+                ));
+            }
+        }
+        self.initiation_attempts += 1;
+        self.completed_at = None;
+        Ok(())
+    }
+
+    /// Whether the session is completed, failed or expired.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self.state,
+            SessionState::Completed(_)
+                | SessionState::Failed(_)
+                | SessionState::Expired
+                | SessionState::Locked
+        )
+    }
+
+    pub fn is_expired(&self, now: OffsetDateTime) -> bool {
+        matches!(
+            self.state,
+            SessionState::Created | SessionState::Initiated { .. }
+        ) && self
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= now.unix_timestamp())
+    }
+}
+
+fn hash_client_secret(client_secret: &str, pepper: Option<&[u8]>) -> Vec<u8> {
+    match pepper {
+        Some(pepper) => {
+            let mut mac =
+                HmacSha512::new_from_slice(pepper).expect("HMAC can take a key of any size");
+            mac.update(client_secret.as_bytes());
+            mac.finalize().into_bytes().to_vec()
+        }
+        None => Sha512::digest(client_secret.as_bytes()).to_vec(),
+    }
 }
 
-pub struct SessionStorage(Arc<dyn DcApiSessionEngine>);
+pub struct SessionStorage {
+    engine: SharedSessionEngine,
+    pepper: Option<Vec<u8>>,
+    session_ttl: Option<Duration>,
+    max_session_age: Duration,
+    max_initiation_attempts: u32,
+    max_failed_submissions: u32,
+    event_hooks: Vec<Arc<dyn SessionEventHook>>,
+    event_spawner: Option<Arc<dyn EventSpawner>>,
+    clock: Arc<dyn Clock>,
+}
 
 impl SessionStorage {
-    pub fn new(engine: Arc<dyn DcApiSessionEngine>) -> Self {
-        Self(engine)
+    pub fn new(engine: SharedSessionEngine) -> Self {
+        Self {
+            engine,
+            pepper: None,
+            session_ttl: None,
+            max_session_age: DEFAULT_MAX_SESSION_AGE,
+            max_initiation_attempts: DEFAULT_MAX_INITIATION_ATTEMPTS,
+            max_failed_submissions: DEFAULT_MAX_FAILED_SUBMISSIONS,
+            event_hooks: Vec::new(),
+            event_spawner: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Keys client secret hashes with a server-side pepper (HMAC-SHA512).
+    ///
+    /// Sessions created without the pepper can no longer be authenticated once it is set.
+    pub fn with_pepper(mut self, pepper: Vec<u8>) -> Self {
+        self.pepper = Some(pepper);
+        self
+    }
+
+    /// Replaces the [SystemClock] used for session expiry and event timestamps.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
     }
 
-    pub async fn new_session(&self) -> Result<SessionCreation> {
-        let session_creation = Session::new_with_creation_response()?;
-        self.0
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// Sessions created from now on expire if they are not completed within `ttl`.
+    pub fn set_session_ttl(&mut self, ttl: Duration) {
+        self.session_ttl = Some(ttl);
+    }
+
+    /// Age after which [SessionStorage::sweep] expires sessions created without
+    /// a TTL that were not completed. Defaults to one day.
+    pub fn set_max_session_age(&mut self, max_age: Duration) {
+        self.max_session_age = max_age;
+    }
+
+    pub fn set_max_initiation_attempts(&mut self, max_attempts: u32) {
+        self.max_initiation_attempts = max_attempts;
+    }
+
+    pub fn max_initiation_attempts(&self) -> u32 {
+        self.max_initiation_attempts
+    }
+
+    pub fn set_max_failed_submissions(&mut self, max_failures: u32) {
+        self.max_failed_submissions = max_failures;
+    }
+
+    pub fn max_failed_submissions(&self) -> u32 {
+        self.max_failed_submissions
+    }
+
+    pub fn add_event_hook(&mut self, hook: Arc<dyn SessionEventHook>) {
+        self.event_hooks.push(hook);
+    }
+
+    /// Delivers events to the hooks in the background with `spawner`, instead
+    /// of awaiting each hook before returning from the state transition.
+    ///
+    /// `None` restores the inline delivery.
+    pub fn set_event_spawner(&mut self, spawner: Option<Arc<dyn EventSpawner>>) {
+        self.event_spawner = spawner;
+    }
+
+    pub async fn new_session(&self, binding: Option<SessionBinding>) -> Result<SessionCreation> {
+        let mut session_creation = Session::new_with_creation_response(self.pepper.as_deref())?;
+        let now = self.clock.now();
+        session_creation.session.binding = binding;
+        session_creation.session.created_at = Some(now.unix_timestamp());
+        session_creation.session.expires_at =
+            self.session_ttl.map(|ttl| (now + ttl).unix_timestamp());
+        self.engine
             .new_session(
                 session_creation.session_creation_response.id.clone(),
                 session_creation.session.clone(),
             )
             .await?;
 
+        self.emit(SessionEvent::new(
+            session_creation.session_creation_response.id.clone(),
+            SessionEventKind::Created,
+            now,
+        ))
+        .await;
+
         Ok(session_creation)
     }
 
     pub async fn get_session(&self, id: String, client_secret: String) -> Result<Option<Session>> {
-        let session = self.0.get_session(id, client_secret.clone()).await?;
+        let session = self
+            .engine
+            .get_session(id.clone(), client_secret.clone())
+            .await?;
         let session = if let Some(s) = session {
             s
         } else {
             return Ok(None);
         };
-        let client_secret_hash = Sha512::digest(client_secret.as_bytes());
         // Returning Ok(None) to avoid leaking information
-        let session_client_secret_hash =
-            match BASE64_STANDARD.decode(session.client_secret_hash.clone()) {
-                Ok(h) => h,
-                Err(e) => {
-                    tracing::debug!("Couldn't decode base64 client secret: {e:?}");
-                    return Ok(None);
-                }
-            };
-        if session_client_secret_hash != client_secret_hash.to_vec() {
-            tracing::debug!("Client secret mismatch");
+        if let Err(e) = Session::check_client_secret(
+            &client_secret,
+            &session.client_secret_hash,
+            self.pepper.as_deref(),
+        ) {
+            tracing::debug!("Client secret check failed: {e:?}");
             return Ok(None);
         }
-        Ok(Some(session))
+        Ok(Some(self.expire_if_due(id, session).await?))
     }
 
+    /// Returns the session as stored, without requiring its client secret.
+    ///
+    /// Unlike [SessionStorage::get_session], an overdue session is not moved to
+    /// [SessionState::Expired]: callers without the secret never trigger state
+    /// transitions, which are left to authenticated calls and [SessionStorage::sweep].
     pub async fn get_session_unauthenticated(&self, id: String) -> Result<Option<Session>> {
-        let session = self.0.get_session_unauthenticated(id).await?;
-        let session = if let Some(s) = session {
-            s
+        self.engine.get_session_unauthenticated(id).await
+    }
+
+    /// Returns the coarse status of a session without requiring its client secret,
+    /// e.g. for a front-end polling for completion.
+    ///
+    /// An overdue session is reported as expired, but is left as stored.
+    pub async fn get_status(&self, id: String) -> Result<Option<SessionStatus>> {
+        Ok(self
+            .get_session_unauthenticated(id)
+            .await?
+            .map(|session| session.status(self.clock.now())))
+    }
+
+    /// Returns the attempt counters of a session without requiring its client
+    /// secret, e.g. for rate limiting at the edge.
+    pub async fn get_counters(&self, id: String) -> Result<Option<SessionCounters>> {
+        Ok(self
+            .get_session_unauthenticated(id)
+            .await?
+            .map(|session| session.counters()))
+    }
+
+    /// Persists the session and notifies the event hooks of its new state.
+    pub async fn update_session(&self, session_id: String, mut session: Session) -> Result<()> {
+        let now = self.clock.now();
+        if session.is_terminal() && session.completed_at.is_none() {
+            session.completed_at = Some(now.unix_timestamp());
+        }
+        let event = SessionEvent::from_state(session_id.clone(), &session.state, now);
+        self.engine.update_session(session_id, session).await?;
+        self.emit(event).await;
+        Ok(())
+    }
+
+    /// Persists the session without notifying the event hooks, for changes
+    /// that are not state transitions (e.g. counters).
+    pub async fn store_session(&self, session_id: String, session: Session) -> Result<()> {
+        self.engine.update_session(session_id, session).await
+    }
+
+    pub async fn remove_session(&self, session_id: String) -> Result<()> {
+        self.engine.remove_session(session_id).await
+    }
+
+    /// Checks that a response can be submitted for the session, and returns its
+    /// initiated annex states.
+    ///
+    /// A submission from another origin or client, or for a session that is
+    /// not initiated, counts as a failed submission, see
+    /// [SessionStorage::record_failed_submission].
+    pub async fn begin_submission(
+        &self,
+        session_id: String,
+        session: &Session,
+        origin: Option<&str>,
+        client_binding: Option<&str>,
+    ) -> Result<
+        (
+            super::annex_c::InitiatedSessionState,
+            super::annex_d::InitiatedSessionState,
+        ),
+        DcApiError,
+    > {
+        if session.state == SessionState::Locked {
+            return Err(DcApiError::SessionLocked);
+        }
+        if let Some(Err(e)) = session
+            .binding
+            .as_ref()
+            .map(|binding| binding.check(origin.unwrap_or_default(), client_binding))
+        {
+            return Err(self
+                .record_failed_submission(session_id, session.clone(), e)
+                .await);
+        }
+        let SessionState::Initiated { annex_c, annex_d } = &session.state else {
+            let e = DcApiError::InvalidSessionState("session has not been initiated".to_string());
+            return Err(self
+                .record_failed_submission(session_id, session.clone(), e)
+                .await);
+        };
+        Ok((annex_c.clone(), annex_d.clone()))
+    }
+
+    /// Counts a failed submission of `session` and persists it, see
+    /// [Session::record_failed_submission].
+    ///
+    /// Returns `error`, to be surfaced to the caller.
+    pub async fn record_failed_submission(
+        &self,
+        session_id: String,
+        mut session: Session,
+        error: DcApiError,
+    ) -> DcApiError {
+        let result = if session.record_failed_submission(self.max_failed_submissions) {
+            self.update_session(session_id, session).await
         } else {
-            return Ok(None);
+            self.store_session(session_id, session).await
         };
-        Ok(Some(session))
+        if let Err(e) = result {
+            return DcApiError::Storage(e.to_string());
+        }
+        error
     }
 
-    pub async fn update_session(&self, session_id: String, session: Session) -> Result<()> {
-        self.0.update_session(session_id, session).await
+    /// Removes the session along with its linked `openid4vp` session.
+    pub async fn remove_session_and_linked<S: SessionStore + ?Sized>(
+        &self,
+        session_id: String,
+        oid4vp_store: &S,
+    ) -> Result<()> {
+        if let Some(session) = self
+            .engine
+            .get_session_unauthenticated(session_id.clone())
+            .await?
+        {
+            remove_oid4vp_session(&session, oid4vp_store).await;
+        }
+        self.engine.remove_session(session_id).await
     }
 
-    pub async fn remove_session(&self, session_id: String) -> Result<()> {
-        self.0.remove_session(session_id).await
+    /// Removes expired sessions, and completed or failed sessions whose outcome
+    /// has been kept for longer than `retention`, along with their linked
+    /// `openid4vp` sessions.
+    ///
+    /// Sessions created without a TTL expire once they are older than the
+    /// maximum session age, see [SessionStorage::set_max_session_age]. The
+    /// retention of terminal sessions stored without a completion time runs
+    /// from their creation or, for sessions stored without either, from the
+    /// first sweep that sees them.
+    pub async fn sweep<S: SessionStore + ?Sized>(
+        &self,
+        retention: Duration,
+        oid4vp_store: &S,
+    ) -> Result<SweepReport> {
+        let now = self.clock.now();
+        let mut report = SweepReport::default();
+        for session_id in self.engine.list_session_ids().await? {
+            let Some(mut session) = self
+                .engine
+                .get_session_unauthenticated(session_id.clone())
+                .await?
+            else {
+                continue;
+            };
+            if session.created_at.is_none() && session.completed_at.is_none() {
+                // Stored before timestamps were recorded: start counting now.
+                if session.is_terminal() {
+                    session.completed_at = Some(now.unix_timestamp());
+                } else {
+                    session.created_at = Some(now.unix_timestamp());
+                }
+                self.store_session(session_id, session).await?;
+                continue;
+            }
+            let over_age = session.expires_at.is_none()
+                && !session.is_terminal()
+                && session.created_at.is_some_and(|created_at| {
+                    created_at.saturating_add(self.max_session_age.whole_seconds())
+                        <= now.unix_timestamp()
+                });
+            if session.is_expired(now) || over_age {
+                self.emit(SessionEvent::new(
+                    session_id.clone(),
+                    SessionEventKind::Expired,
+                    now,
+                ))
+                .await;
+                report.expired += 1;
+            } else if session.state == SessionState::Expired {
+                report.expired += 1;
+            } else if session.is_terminal()
+                && session
+                    .completed_at
+                    .or(session.created_at)
+                    .is_some_and(|completed_at| {
+                        completed_at.saturating_add(retention.whole_seconds())
+                            <= now.unix_timestamp()
+                    })
+            {
+                report.purged += 1;
+            } else {
+                continue;
+            }
+            remove_oid4vp_session(&session, oid4vp_store).await;
+            self.engine.remove_session(session_id).await?;
+        }
+        Ok(report)
+    }
+
+    async fn expire_if_due(&self, session_id: String, mut session: Session) -> Result<Session> {
+        if session.is_expired(self.clock.now()) {
+            session.state = SessionState::Expired;
+            self.update_session(session_id, session.clone()).await?;
+        }
+        Ok(session)
+    }
+
+    async fn emit(&self, event: SessionEvent) {
+        for hook in &self.event_hooks {
+            let hook = hook.clone();
+            let event = event.clone();
+            let delivery = async move {
+                if let Err(e) = hook.on_event(&event).await {
+                    tracing::warn!("Session event hook failed: {e:?}");
+                }
+            };
+            match &self.event_spawner {
+                Some(spawner) => spawner.spawn(Box::pin(delivery)),
+                None => delivery.await,
+            }
+        }
+    }
+}
+
+/// Removes the `openid4vp` session linked to `session`, if any.
+pub async fn remove_oid4vp_session<S: SessionStore + ?Sized>(session: &Session, oid4vp_store: &S) {
+    let Some(oid4vp_session_id) = &session.oid4vp_session_id else {
+        return;
+    };
+    let uuid = match oid4vp_session_id.parse::<Uuid>() {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            tracing::warn!("Invalid OID4VP session id {oid4vp_session_id}: {e:?}");
+            return;
+        }
+    };
+    // The OID4VP session may already be gone, which must not prevent the
+    // DC API session from being removed.
+    if let Err(e) = oid4vp_store.remove_session(uuid).await {
+        tracing::debug!("Could not remove OID4VP session {oid4vp_session_id}: {e:?}");
+    }
+}
+
+/// Updates the status of the `openid4vp` session `oid4vp_session_id`.
+///
+/// The status only mirrors the DC API session for OpenID4VP front-ends, so a
+/// failed write is logged rather than failing the submission.
+pub async fn update_oid4vp_status<S: SessionStore + ?Sized>(
+    oid4vp_store: &S,
+    oid4vp_session_id: &str,
+    status: Status,
+) {
+    let uuid = match oid4vp_session_id.parse::<Uuid>() {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            tracing::warn!("Invalid OID4VP session id {oid4vp_session_id}: {e:?}");
+            return;
+        }
+    };
+    if let Err(e) = oid4vp_store.update_status(uuid, status).await {
+        tracing::warn!("Could not update OID4VP session {oid4vp_session_id} status: {e:?}");
+    }
+}
+
+/// The `openid4vp` session outcome of a submission. The cause of an error
+/// is the JSON encoded `code` and `details` of the [DcApiError].
+pub fn oid4vp_outcome(result: &Result<VerificationResult, DcApiError>) -> Outcome {
+    match result {
+        Ok(result) if result.verdict.is_verified() => Outcome::Success {
+            info: serde_json::to_value(&result.outcome).unwrap_or_default(),
+        },
+        Ok(result) => Outcome::Failure {
+            reason: serde_json::to_string(&result.verdict).unwrap_or_default(),
+        },
+        Err(e) => Outcome::Error {
+            cause: serde_json::json!({ "code": e.code(), "details": e.details() }).to_string(),
+        },
     }
 }
 
+/// See [Session::counters].
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct SessionCounters {
+    pub initiation_attempts: u32,
+    pub failed_submission_attempts: u32,
+    pub locked: bool,
+}
+
+/// Number of sessions removed by [SessionStorage::sweep].
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct SweepReport {
+    pub expired: usize,
+    pub purged: usize,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct SessionCreationResponse {
     id: String,
     client_secret: String,
 }
 
+/// See [Session::status].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SessionStatus {
+    Created,
+    Initiated,
+    Completed,
+    Failed,
+    Expired,
+    Locked,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum SessionState {
     Created,
@@ -139,5 +722,298 @@ pub enum SessionState {
         annex_c: super::annex_c::InitiatedSessionState,
         annex_d: super::annex_d::InitiatedSessionState,
     },
-    Completed(ResponseAuthenticationOutcome),
+    Completed(VerificationResult),
+    /// The response could not be processed.
+    Failed(String),
+    /// The session was not completed before its expiry.
+    Expired,
+    /// Too many submissions failed, see [SessionStorage::set_max_failed_submissions].
+    Locked,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, sync::Mutex};
+
+    use super::*;
+    use crate::{clock::FixedClock, sealed::SealedOid4VpSessionStore};
+
+    #[derive(Default)]
+    struct MemoryEngine(Mutex<BTreeMap<String, Session>>);
+
+    #[async_trait]
+    impl DcApiSessionEngine for MemoryEngine {
+        async fn new_session(&self, session_id: String, session: Session) -> Result<()> {
+            self.0.lock().unwrap().insert(session_id, session);
+            Ok(())
+        }
+
+        async fn get_session(&self, id: String, _client_secret: String) -> Result<Option<Session>> {
+            self.get_session_unauthenticated(id).await
+        }
+
+        async fn get_session_unauthenticated(&self, id: String) -> Result<Option<Session>> {
+            Ok(self.0.lock().unwrap().get(&id).cloned())
+        }
+
+        async fn update_session(&self, session_id: String, session: Session) -> Result<()> {
+            self.new_session(session_id, session).await
+        }
+
+        async fn remove_session(&self, session_id: String) -> Result<()> {
+            self.0.lock().unwrap().remove(&session_id);
+            Ok(())
+        }
+
+        async fn list_session_ids(&self) -> Result<Vec<String>> {
+            Ok(self.0.lock().unwrap().keys().cloned().collect())
+        }
+    }
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn storage(engine: Arc<MemoryEngine>, now: i64) -> SessionStorage {
+        let mut storage = SessionStorage::new(engine);
+        storage.set_clock(Arc::new(FixedClock(
+            OffsetDateTime::from_unix_timestamp(now).unwrap(),
+        )));
+        storage
+    }
+
+    fn session(state: SessionState, created_at: Option<i64>, completed_at: Option<i64>) -> Session {
+        let mut session = Session::new_with_creation_response(None).unwrap().session;
+        session.state = state;
+        session.created_at = created_at;
+        session.completed_at = completed_at;
+        session
+    }
+
+    async fn sweep(engine: &Arc<MemoryEngine>, now: i64) -> SweepReport {
+        storage(engine.clone(), now)
+            .sweep(Duration::hours(1), &SealedOid4VpSessionStore)
+            .await
+            .unwrap()
+    }
+
+    fn stored(engine: &MemoryEngine, id: &str) -> Option<Session> {
+        engine.0.lock().unwrap().get(id).cloned()
+    }
+
+    #[tokio::test]
+    async fn keeps_legacy_terminal_sessions_for_the_retention_period() {
+        let engine = Arc::new(MemoryEngine::default());
+        let failed = session(SessionState::Failed("error".to_string()), None, None);
+        engine
+            .new_session("legacy".to_string(), failed)
+            .await
+            .unwrap();
+
+        let report = sweep(&engine, NOW).await;
+        assert_eq!(report.purged, 0);
+        assert_eq!(stored(&engine, "legacy").unwrap().completed_at, Some(NOW));
+
+        let report = sweep(&engine, NOW + 3600).await;
+        assert_eq!(report.purged, 1);
+        assert!(stored(&engine, "legacy").is_none());
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_creation_time_for_terminal_sessions() {
+        let engine = Arc::new(MemoryEngine::default());
+        let recent = session(SessionState::Expired, Some(NOW - 60), None);
+        let old = session(SessionState::Locked, Some(NOW - 7200), None);
+        engine
+            .new_session("recent".to_string(), recent)
+            .await
+            .unwrap();
+        engine.new_session("old".to_string(), old).await.unwrap();
+
+        let report = sweep(&engine, NOW).await;
+        assert_eq!(report.expired, 1);
+        assert_eq!(report.purged, 1);
+        assert!(stored(&engine, "old").is_none());
+    }
+
+    #[tokio::test]
+    async fn expires_sessions_without_ttl_after_max_age() {
+        let engine = Arc::new(MemoryEngine::default());
+        let fresh = session(SessionState::Created, Some(NOW - 60), None);
+        let stale = session(SessionState::Created, Some(NOW - 2 * 86400), None);
+        let legacy = session(SessionState::Created, None, None);
+        engine
+            .new_session("fresh".to_string(), fresh)
+            .await
+            .unwrap();
+        engine
+            .new_session("stale".to_string(), stale)
+            .await
+            .unwrap();
+        engine
+            .new_session("legacy".to_string(), legacy)
+            .await
+            .unwrap();
+
+        let report = sweep(&engine, NOW).await;
+        assert_eq!(report.expired, 1);
+        assert!(stored(&engine, "fresh").is_some());
+        assert!(stored(&engine, "stale").is_none());
+        assert_eq!(stored(&engine, "legacy").unwrap().created_at, Some(NOW));
+
+        let report = sweep(&engine, NOW + 86400).await;
+        assert_eq!(report.expired, 2);
+        assert!(engine.0.lock().unwrap().is_empty());
+    }
+
+    fn initiated() -> SessionState {
+        SessionState::Initiated {
+            annex_c: serde_json::from_value(serde_json::json!({
+                "private_key": [],
+                "session_transcript_bytes": [],
+            }))
+            .unwrap(),
+            annex_d: serde_json::from_value(serde_json::json!({
+                "oid4vp_session_id": Uuid::new_v4().to_string(),
+                "origin": "https://rp.example.com",
+                "nonce": "nonce",
+            }))
+            .unwrap(),
+        }
+    }
+
+    async fn submit_from(
+        storage: &SessionStorage,
+        engine: &MemoryEngine,
+        origin: &str,
+    ) -> Result<(), DcApiError> {
+        let session = stored(engine, "session").unwrap();
+        storage
+            .begin_submission("session".to_string(), &session, Some(origin), None)
+            .await
+            .map(|_| ())
+    }
+
+    #[tokio::test]
+    async fn locks_initiated_session_after_failed_submissions() {
+        let engine = Arc::new(MemoryEngine::default());
+        let mut storage = storage(engine.clone(), NOW);
+        storage.set_max_failed_submissions(3);
+        let mut session = session(initiated(), Some(NOW), None);
+        session.binding = Some(SessionBinding::new(
+            "https://rp.example.com".to_string(),
+            None,
+        ));
+        engine
+            .new_session("session".to_string(), session)
+            .await
+            .unwrap();
+
+        for attempt in 1..3 {
+            let e = submit_from(&storage, &engine, "https://evil.example.com")
+                .await
+                .unwrap_err();
+            assert!(matches!(e, DcApiError::SessionBindingMismatch(_)));
+            let session = stored(&engine, "session").unwrap();
+            assert!(matches!(session.state, SessionState::Initiated { .. }));
+            assert_eq!(session.failed_submission_attempts, attempt);
+        }
+        submit_from(&storage, &engine, "https://rp.example.com")
+            .await
+            .unwrap();
+
+        let session = stored(&engine, "session").unwrap();
+        let e = storage
+            .record_failed_submission(
+                "session".to_string(),
+                session,
+                DcApiError::InvalidResponse("bad response".to_string()),
+            )
+            .await;
+        assert!(matches!(e, DcApiError::InvalidResponse(_)));
+        let session = stored(&engine, "session").unwrap();
+        assert_eq!(session.state, SessionState::Locked);
+        assert!(session.counters().locked);
+
+        let e = submit_from(&storage, &engine, "https://rp.example.com")
+            .await
+            .unwrap_err();
+        assert!(matches!(e, DcApiError::SessionLocked));
+        assert_eq!(
+            stored(&engine, "session")
+                .unwrap()
+                .failed_submission_attempts,
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn counts_submissions_for_sessions_that_are_not_initiated() {
+        let engine = Arc::new(MemoryEngine::default());
+        let mut storage = storage(engine.clone(), NOW);
+        storage.set_max_failed_submissions(2);
+        engine
+            .new_session(
+                "session".to_string(),
+                session(SessionState::Created, Some(NOW), None),
+            )
+            .await
+            .unwrap();
+
+        let e = submit_from(&storage, &engine, "https://rp.example.com")
+            .await
+            .unwrap_err();
+        assert!(matches!(e, DcApiError::InvalidSessionState(_)));
+        assert_eq!(
+            stored(&engine, "session").unwrap().state,
+            SessionState::Created
+        );
+        let e = submit_from(&storage, &engine, "https://rp.example.com")
+            .await
+            .unwrap_err();
+        assert!(matches!(e, DcApiError::InvalidSessionState(_)));
+        assert_eq!(
+            stored(&engine, "session").unwrap().state,
+            SessionState::Locked
+        );
+        let e = submit_from(&storage, &engine, "https://rp.example.com")
+            .await
+            .unwrap_err();
+        assert!(matches!(e, DcApiError::SessionLocked));
+    }
+
+    #[tokio::test]
+    async fn keeps_outcome_of_completed_session() {
+        let engine = Arc::new(MemoryEngine::default());
+        let mut storage = storage(engine.clone(), NOW);
+        storage.set_max_failed_submissions(1);
+        let completed = SessionState::Completed(VerificationResult::from(
+            isomdl::presentation::authentication::ResponseAuthenticationOutcome::default(),
+        ));
+        engine
+            .new_session(
+                "session".to_string(),
+                session(completed.clone(), Some(NOW), Some(NOW)),
+            )
+            .await
+            .unwrap();
+
+        let e = submit_from(&storage, &engine, "https://rp.example.com")
+            .await
+            .unwrap_err();
+        assert!(matches!(e, DcApiError::InvalidSessionState(_)));
+        let session = stored(&engine, "session").unwrap();
+        assert_eq!(session.state, completed);
+        assert_eq!(session.failed_submission_attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn records_creation_time() {
+        let engine = Arc::new(MemoryEngine::default());
+        let creation = storage(engine.clone(), NOW)
+            .new_session(None)
+            .await
+            .unwrap();
+        let session = stored(&engine, &creation.session_creation_response.id).unwrap();
+        assert_eq!(session.created_at, Some(NOW));
+        assert_eq!(session.expires_at, None);
+    }
 }
@@ -0,0 +1,174 @@
+use anyhow::{Context, Result, bail};
+use base64::prelude::*;
+use p256::ecdsa::signature::Verifier;
+use pkcs8::{DecodePublicKey, der::Encode};
+use serde_json::Value;
+use x509_cert::{
+    Certificate,
+    ext::pkix::{BasicConstraints, KeyUsage},
+    spki::SubjectPublicKeyInfoOwned,
+};
+
+/// How an ECDSA signature is encoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SignatureEncoding {
+    /// ASN.1 DER, as in X.509 certificates and CRLs.
+    Der,
+    /// Fixed-size `r || s`, as in COSE and JWS.
+    Fixed,
+}
+
+/// Verifies an ECDSA signature over `message` with a P-256 (SHA-256) or
+/// P-384 (SHA-384) public key.
+pub(crate) fn verify_ecdsa(
+    public_key: &SubjectPublicKeyInfoOwned,
+    message: &[u8],
+    signature: &[u8],
+    encoding: SignatureEncoding,
+) -> Result<()> {
+    let public_key = public_key.to_der().context("Could not encode public key")?;
+
+    if let Ok(key) = p256::ecdsa::VerifyingKey::from_public_key_der(&public_key) {
+        let signature = match encoding {
+            SignatureEncoding::Der => p256::ecdsa::Signature::from_der(signature),
+            SignatureEncoding::Fixed => p256::ecdsa::Signature::from_slice(signature),
+        }
+        .context("Could not decode P-256 signature")?;
+        return key
+            .verify(message, &signature)
+            .context("invalid P-256 signature");
+    }
+    if let Ok(key) = p384::ecdsa::VerifyingKey::from_public_key_der(&public_key) {
+        let signature = match encoding {
+            SignatureEncoding::Der => p384::ecdsa::Signature::from_der(signature),
+            SignatureEncoding::Fixed => p384::ecdsa::Signature::from_slice(signature),
+        }
+        .context("Could not decode P-384 signature")?;
+        return key
+            .verify(message, &signature)
+            .context("invalid P-384 signature");
+    }
+    bail!("unsupported public key, expected P-256 or P-384")
+}
+
+/// Checks that the JWS `alg` matches the curve of `public_key`, ES256 for
+/// P-256 and ES384 for P-384.
+pub(crate) fn check_jws_algorithm(alg: &str, public_key: &SubjectPublicKeyInfoOwned) -> Result<()> {
+    let public_key = public_key.to_der().context("Could not encode public key")?;
+    let expected = if p256::ecdsa::VerifyingKey::from_public_key_der(&public_key).is_ok() {
+        "ES256"
+    } else if p384::ecdsa::VerifyingKey::from_public_key_der(&public_key).is_ok() {
+        "ES384"
+    } else {
+        bail!("unsupported public key, expected P-256 or P-384");
+    };
+    if alg != expected {
+        bail!("algorithm {alg} does not match the public key, expected {expected}");
+    }
+    Ok(())
+}
+
+/// Checks that the JWS `alg` matches the `crv` of `jwk`, ES256 for P-256 and
+/// ES384 for P-384.
+pub(crate) fn check_jwk_algorithm(alg: &str, jwk: &Value) -> Result<()> {
+    let expected = match jwk.get("crv").and_then(Value::as_str) {
+        Some("P-256") => "ES256",
+        Some("P-384") => "ES384",
+        crv => bail!("unsupported JWK curve {crv:?}, expected P-256 or P-384"),
+    };
+    if alg != expected {
+        bail!("algorithm {alg} does not match the JWK, expected {expected}");
+    }
+    Ok(())
+}
+
+/// Verifies a fixed-size JWS ECDSA signature over `message` with a P-256 or
+/// P-384 public JWK.
+pub(crate) fn verify_ecdsa_jwk(jwk: &Value, message: &[u8], signature: &[u8]) -> Result<()> {
+    if jwk.get("kty").and_then(Value::as_str) != Some("EC") {
+        bail!("unsupported JWK, expected an EC key");
+    }
+    let coordinate = |name: &str| {
+        let coordinate = jwk
+            .get(name)
+            .and_then(Value::as_str)
+            .with_context(|| format!("JWK has no {name} coordinate"))?;
+        BASE64_URL_SAFE_NO_PAD
+            .decode(coordinate)
+            .with_context(|| format!("Could not decode JWK {name} coordinate"))
+    };
+    // SEC1 uncompressed point.
+    let mut point = vec![0x04];
+    point.extend(coordinate("x")?);
+    point.extend(coordinate("y")?);
+
+    match jwk.get("crv").and_then(Value::as_str) {
+        Some("P-256") => {
+            let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(&point)
+                .context("Could not decode P-256 JWK")?;
+            let signature = p256::ecdsa::Signature::from_slice(signature)
+                .context("Could not decode P-256 signature")?;
+            key.verify(message, &signature)
+                .context("invalid P-256 signature")
+        }
+        Some("P-384") => {
+            let key = p384::ecdsa::VerifyingKey::from_sec1_bytes(&point)
+                .context("Could not decode P-384 JWK")?;
+            let signature = p384::ecdsa::Signature::from_slice(signature)
+                .context("Could not decode P-384 signature")?;
+            key.verify(message, &signature)
+                .context("invalid P-384 signature")
+        }
+        crv => bail!("unsupported JWK curve {crv:?}, expected P-256 or P-384"),
+    }
+}
+
+/// Verifies that `certificate` was issued, and signed, by `issuer`.
+pub(crate) fn verify_issued_by(certificate: &Certificate, issuer: &Certificate) -> Result<()> {
+    if issuer.tbs_certificate.subject != certificate.tbs_certificate.issuer {
+        bail!(
+            "{} is not issued by {}",
+            certificate.tbs_certificate.subject,
+            issuer.tbs_certificate.subject
+        );
+    }
+    let message = certificate
+        .tbs_certificate
+        .to_der()
+        .context("Could not encode certificate")?;
+    let signature = certificate
+        .signature
+        .as_bytes()
+        .context("certificate signature has unused bits")?;
+    verify_ecdsa(
+        &issuer.tbs_certificate.subject_public_key_info,
+        &message,
+        signature,
+        SignatureEncoding::Der,
+    )
+}
+
+/// The key usage of `certificate`, if it has the extension.
+pub(crate) fn key_usage(certificate: &Certificate) -> Result<Option<KeyUsage>> {
+    Ok(certificate
+        .tbs_certificate
+        .get::<KeyUsage>()
+        .context("Could not decode key usage")?
+        .map(|(_, usage)| usage))
+}
+
+/// Checks that `certificate` is a CA allowed to sign certificates.
+pub(crate) fn check_issuing_certificate(certificate: &Certificate) -> Result<()> {
+    let is_ca = certificate
+        .tbs_certificate
+        .get::<BasicConstraints>()
+        .context("Could not decode basic constraints")?
+        .is_some_and(|(_, constraints)| constraints.ca);
+    if !is_ca || !key_usage(certificate)?.is_some_and(|usage| usage.key_cert_sign()) {
+        bail!(
+            "{} is not a CA allowed to sign certificates",
+            certificate.tbs_certificate.subject
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,672 @@
+use std::{collections::HashMap, io::Read, sync::Mutex};
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::prelude::*;
+use ciborium::Value as Cbor;
+use coset::{
+    CborSerializable, CoseSign1, Label, RegisteredLabelWithPrivate, TaggedCborSerializable, iana,
+};
+use flate2::read::ZlibDecoder;
+use isomdl::{
+    cbor,
+    definitions::{
+        device_response::Document, helpers::Tag24, x509::x5chain::X5CHAIN_COSE_HEADER_LABEL,
+    },
+};
+use pkcs8::der::Decode;
+use serde::Deserialize;
+use serde_json::json;
+use time::OffsetDateTime;
+use tracing::debug;
+use x509_cert::Certificate;
+
+use crate::{
+    fetch::SharedFetcher,
+    signature::{
+        SignatureEncoding, check_issuing_certificate, check_jws_algorithm, key_usage, verify_ecdsa,
+        verify_issued_by,
+    },
+    validity::check_certificate_validity,
+    verdict::{RevocationReport, VerdictReason},
+};
+
+/// Code of the [VerdictReason] reporting a credential marked as invalid in its status list.
+pub const CREDENTIAL_REVOKED: &str = "credential_revoked";
+/// Code of the [VerdictReason] reporting a credential marked as suspended in its status list.
+pub const CREDENTIAL_SUSPENDED: &str = "credential_suspended";
+/// Code of the [VerdictReason] reporting a credential whose status list could not be checked.
+pub const STATUS_LIST_UNAVAILABLE: &str = "status_list_unavailable";
+
+/// Maximum size of a decompressed status list, 16 MiB or 128 million
+/// credentials at one bit per status.
+const MAX_STATUS_LIST_SIZE: u64 = 16 * 1024 * 1024;
+
+const STATUS_VALID: u8 = 0x00;
+const STATUS_INVALID: u8 = 0x01;
+const STATUS_SUSPENDED: u8 = 0x02;
+
+/// CWT claim keys of the Token Status List.
+const CWT_SUB: i64 = 2;
+const CWT_EXP: i64 = 4;
+const CWT_TTL: i64 = 65534;
+const CWT_STATUS_LIST: i64 = 65533;
+/// COSE header parameter of the content type of the token (RFC 9596).
+const COSE_TYP: i64 = 16;
+
+/// The `status_list` entry of the `status` of a Mobile Security Object or
+/// an SD-JWT VC.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct StatusListReference {
+    pub idx: u64,
+    pub uri: String,
+}
+
+/// Decodes the status list reference from the Mobile Security Object of
+/// `document`, if it has one. A `status` or `status_list` that is not a map is
+/// an error.
+pub fn mso_status_list(document: &Document) -> Result<Option<StatusListReference>> {
+    let payload = document
+        .issuer_signed
+        .issuer_auth
+        .payload
+        .as_ref()
+        .context("issuer auth has no payload")?;
+    let mso: Tag24<Cbor> = cbor::from_slice(payload).context("Could not deserialize MSO")?;
+    let mso = mso.into_inner();
+    let Some(status) = map_get(&mso, "status") else {
+        return Ok(None);
+    };
+    if status.as_map().is_none() {
+        bail!("MSO status is not a map");
+    }
+    let Some(status_list) = map_get(status, "status_list") else {
+        return Ok(None);
+    };
+    if status_list.as_map().is_none() {
+        bail!("MSO status_list is not a map");
+    }
+    let idx = map_get(status_list, "idx")
+        .and_then(|idx| idx.as_integer())
+        .and_then(|idx| u64::try_from(idx).ok())
+        .context("status_list idx is missing")?;
+    let uri = map_get(status_list, "uri")
+        .and_then(|uri| uri.as_text())
+        .context("status_list uri is missing")?;
+    Ok(Some(StatusListReference {
+        idx,
+        uri: uri.to_string(),
+    }))
+}
+
+/// Checks credentials against the IETF Token Status List referenced from
+/// their MSO, in either its JWT or CWT form, caching each list until it expires.
+///
+/// The status list token must be signed by the document signer, or by a
+/// certificate allowed to sign issued by one of the CAs of the document's
+/// certification path.
+pub struct StatusListChecker {
+    fetcher: SharedFetcher,
+    cache: Mutex<HashMap<String, StatusListToken>>,
+}
+
+/// A status list and the certificate its token is signed with.
+#[derive(Clone, Debug)]
+struct StatusListToken {
+    signer: Certificate,
+    status_list: StatusList,
+}
+
+#[derive(Clone, Debug)]
+struct StatusList {
+    bits: u8,
+    /// The decompressed status list.
+    lst: Vec<u8>,
+    /// Unix timestamp after which the list must be fetched again.
+    expires_at: Option<i64>,
+}
+
+impl StatusListChecker {
+    pub fn new(fetcher: SharedFetcher) -> Self {
+        Self {
+            fetcher,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn check(
+        &self,
+        reference: &StatusListReference,
+        path: &[Certificate],
+        now: OffsetDateTime,
+    ) -> RevocationReport {
+        let mut report = RevocationReport::default();
+        let status = self
+            .status_list(&reference.uri, path, now)
+            .await
+            .and_then(|status_list| status_list.status(reference.idx));
+        let reason = |code: &str, detail: String| VerdictReason {
+            code: code.to_string(),
+            detail: json!({
+                "uri": reference.uri,
+                "idx": reference.idx,
+                "detail": detail,
+            }),
+        };
+        match status {
+            Ok(STATUS_VALID) => {}
+            Ok(STATUS_INVALID) => report
+                .revoked
+                .push(reason(CREDENTIAL_REVOKED, "invalid".to_string())),
+            Ok(STATUS_SUSPENDED) => report
+                .suspended
+                .push(reason(CREDENTIAL_SUSPENDED, "suspended".to_string())),
+            Ok(status) => report.unknown.push(reason(
+                STATUS_LIST_UNAVAILABLE,
+                format!("unsupported status {status:#04x}"),
+            )),
+            Err(e) => report
+                .unknown
+                .push(reason(STATUS_LIST_UNAVAILABLE, format!("{e:#}"))),
+        }
+        report
+    }
+
+    /// Returns the status list at `uri`, from the cache if it has not expired.
+    ///
+    /// The signer of a cached list is checked again against `path`, as the
+    /// cache is keyed by URI only.
+    async fn status_list(
+        &self,
+        uri: &str,
+        path: &[Certificate],
+        now: OffsetDateTime,
+    ) -> Result<StatusList> {
+        let cached = self
+            .cache
+            .lock()
+            .map_err(|_| anyhow!("status list cache is poisoned"))?
+            .get(uri)
+            .cloned();
+        if let Some(cached) = cached
+            && cached
+                .status_list
+                .expires_at
+                .is_some_and(|expires_at| now.unix_timestamp() < expires_at)
+        {
+            check_signer(&cached.signer, path, now)?;
+            return Ok(cached.status_list);
+        }
+
+        debug!("fetching status list from {uri}");
+        let body = self.fetcher.fetch(uri).await?;
+        let token = match std::str::from_utf8(&body) {
+            Ok(jwt) if jwt.trim().split('.').count() == 3 => {
+                decode_jwt(jwt.trim(), uri, path, now)?
+            }
+            _ => decode_cwt(&body, uri, path, now)?,
+        };
+
+        // Lists without exp or ttl are fetched again on every check.
+        if token.status_list.expires_at.is_some() {
+            self.cache
+                .lock()
+                .map_err(|_| anyhow!("status list cache is poisoned"))?
+                .insert(uri.to_string(), token.clone());
+        }
+        Ok(token.status_list)
+    }
+}
+
+impl StatusList {
+    fn new(bits: u64, compressed: &[u8], expires_at: Option<i64>) -> Result<Self> {
+        let bits = match bits {
+            1 | 2 | 4 | 8 => bits as u8,
+            _ => bail!("unsupported status list bits {bits}"),
+        };
+        let mut lst = Vec::new();
+        ZlibDecoder::new(compressed)
+            .take(MAX_STATUS_LIST_SIZE + 1)
+            .read_to_end(&mut lst)
+            .context("Could not decompress status list")?;
+        if lst.len() as u64 > MAX_STATUS_LIST_SIZE {
+            bail!("status list exceeds {MAX_STATUS_LIST_SIZE} bytes");
+        }
+        Ok(Self {
+            bits,
+            lst,
+            expires_at,
+        })
+    }
+
+    fn status(&self, idx: u64) -> Result<u8> {
+        let position = idx
+            .checked_mul(self.bits as u64)
+            .context("status list index is too large")?;
+        let byte = usize::try_from(position / 8).context("status list index is too large")?;
+        let shift = (position % 8) as u8;
+        let byte = self
+            .lst
+            .get(byte)
+            .context("status list index is out of range")?;
+        let mask = ((1u16 << self.bits) - 1) as u8;
+        Ok((byte >> shift) & mask)
+    }
+}
+
+/// Expiry of a status list token, as the earliest of `exp` and now + `ttl`.
+fn expires_at(exp: Option<i64>, ttl: Option<i64>, now: OffsetDateTime) -> Option<i64> {
+    let ttl = ttl.map(|ttl| now.unix_timestamp().saturating_add(ttl));
+    match (exp, ttl) {
+        (Some(exp), Some(ttl)) => Some(exp.min(ttl)),
+        (exp, ttl) => exp.or(ttl),
+    }
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+    #[serde(default)]
+    typ: Option<String>,
+    x5c: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct JwtClaims {
+    sub: String,
+    #[serde(default)]
+    exp: Option<i64>,
+    #[serde(default)]
+    ttl: Option<i64>,
+    status_list: JwtStatusList,
+}
+
+#[derive(Deserialize)]
+struct JwtStatusList {
+    bits: u64,
+    lst: String,
+}
+
+fn decode_jwt(
+    jwt: &str,
+    uri: &str,
+    path: &[Certificate],
+    now: OffsetDateTime,
+) -> Result<StatusListToken> {
+    let mut parts = jwt.split('.');
+    let (Some(header), Some(claims), Some(signature)) = (parts.next(), parts.next(), parts.next())
+    else {
+        bail!("status list token is not a JWT");
+    };
+    let header: JwtHeader = serde_json::from_slice(
+        &BASE64_URL_SAFE_NO_PAD
+            .decode(header)
+            .context("Could not decode JWT header")?,
+    )
+    .context("Could not deserialize JWT header")?;
+    if header.typ.as_deref() != Some("statuslist+jwt") {
+        bail!("unexpected status list token type {:?}", header.typ);
+    }
+    if !matches!(header.alg.as_str(), "ES256" | "ES384") {
+        bail!("unsupported status list token algorithm {}", header.alg);
+    }
+    let signer = header.x5c.first().context("status list token has no x5c")?;
+    let signer = Certificate::from_der(
+        &BASE64_STANDARD
+            .decode(signer)
+            .context("Could not decode x5c")?,
+    )
+    .context("Could not decode x5c certificate")?;
+    check_signer(&signer, path, now)?;
+    check_jws_algorithm(&header.alg, &signer.tbs_certificate.subject_public_key_info)?;
+    let message_len = jwt.len() - signature.len() - 1;
+    verify_ecdsa(
+        &signer.tbs_certificate.subject_public_key_info,
+        &jwt.as_bytes()[..message_len],
+        &BASE64_URL_SAFE_NO_PAD
+            .decode(signature)
+            .context("Could not decode JWT signature")?,
+        SignatureEncoding::Fixed,
+    )
+    .context("invalid status list token signature")?;
+
+    let claims: JwtClaims = serde_json::from_slice(
+        &BASE64_URL_SAFE_NO_PAD
+            .decode(claims)
+            .context("Could not decode JWT claims")?,
+    )
+    .context("Could not deserialize JWT claims")?;
+    check_claims(&claims.sub, claims.exp, uri, now)?;
+    let lst = BASE64_URL_SAFE_NO_PAD
+        .decode(claims.status_list.lst)
+        .context("Could not decode status list")?;
+    let status_list = StatusList::new(
+        claims.status_list.bits,
+        &lst,
+        expires_at(claims.exp, claims.ttl, now),
+    )?;
+    Ok(StatusListToken {
+        signer,
+        status_list,
+    })
+}
+
+fn decode_cwt(
+    cwt: &[u8],
+    uri: &str,
+    path: &[Certificate],
+    now: OffsetDateTime,
+) -> Result<StatusListToken> {
+    let sign1 = CoseSign1::from_tagged_slice(cwt)
+        .or_else(|_| CoseSign1::from_slice(cwt))
+        .map_err(|e| anyhow!("Could not decode status list CWT: {e:?}"))?;
+    let typ = sign1
+        .protected
+        .header
+        .rest
+        .iter()
+        .find(|(label, _)| *label == Label::Int(COSE_TYP))
+        .and_then(|(_, typ)| typ.as_text());
+    if typ != Some("application/statuslist+cwt") {
+        bail!("unexpected status list token type {typ:?}");
+    }
+    let alg = match &sign1.protected.header.alg {
+        Some(RegisteredLabelWithPrivate::Assigned(iana::Algorithm::ES256)) => "ES256",
+        Some(RegisteredLabelWithPrivate::Assigned(iana::Algorithm::ES384)) => "ES384",
+        alg => bail!("unsupported status list token algorithm {alg:?}"),
+    };
+    let x5chain = sign1
+        .protected
+        .header
+        .rest
+        .iter()
+        .chain(sign1.unprotected.rest.iter())
+        .find(|(label, _)| *label == Label::Int(X5CHAIN_COSE_HEADER_LABEL))
+        .map(|(_, value)| value)
+        .context("status list token has no x5chain")?;
+    let signer = match x5chain {
+        Cbor::Bytes(certificate) => certificate,
+        Cbor::Array(certificates) => certificates
+            .first()
+            .and_then(|certificate| certificate.as_bytes())
+            .context("status list token x5chain is empty")?,
+        _ => bail!("x5chain must be a byte string or an array"),
+    };
+    let signer = Certificate::from_der(signer).context("Could not decode x5chain certificate")?;
+    check_signer(&signer, path, now)?;
+    check_jws_algorithm(alg, &signer.tbs_certificate.subject_public_key_info)?;
+    sign1
+        .verify_signature(b"", |signature, message| {
+            verify_ecdsa(
+                &signer.tbs_certificate.subject_public_key_info,
+                message,
+                signature,
+                SignatureEncoding::Fixed,
+            )
+        })
+        .context("invalid status list token signature")?;
+
+    let claims: Cbor = cbor::from_slice(
+        sign1
+            .payload
+            .as_ref()
+            .context("status list token has no payload")?,
+    )
+    .context("Could not deserialize CWT claims")?;
+    let sub = claim(&claims, CWT_SUB)
+        .and_then(|sub| sub.as_text())
+        .context("status list token has no sub")?;
+    let exp = claim(&claims, CWT_EXP).and_then(as_i64);
+    let ttl = claim(&claims, CWT_TTL).and_then(as_i64);
+    check_claims(sub, exp, uri, now)?;
+    let status_list =
+        claim(&claims, CWT_STATUS_LIST).context("status list token has no status_list")?;
+    let bits = map_get(status_list, "bits")
+        .and_then(as_i64)
+        .and_then(|bits| u64::try_from(bits).ok())
+        .context("status list has no bits")?;
+    let lst = map_get(status_list, "lst")
+        .and_then(|lst| lst.as_bytes())
+        .context("status list has no lst")?;
+    let status_list = StatusList::new(bits, lst, expires_at(exp, ttl, now))?;
+    Ok(StatusListToken {
+        signer,
+        status_list,
+    })
+}
+
+fn check_claims(sub: &str, exp: Option<i64>, uri: &str, now: OffsetDateTime) -> Result<()> {
+    if sub != uri {
+        bail!("status list token sub {sub} does not match {uri}");
+    }
+    if exp.is_some_and(|exp| exp < now.unix_timestamp()) {
+        bail!("status list token has expired");
+    }
+    Ok(())
+}
+
+/// Checks that the status list token `signer` is valid at `now`, and is the
+/// document signer or a certificate allowed to sign, issued by one of the CAs
+/// of the document's certification `path`.
+fn check_signer(signer: &Certificate, path: &[Certificate], now: OffsetDateTime) -> Result<()> {
+    check_certificate_validity(signer, now)?;
+    if path.first() == Some(signer) {
+        return Ok(());
+    }
+    if !key_usage(signer)?.is_some_and(|usage| usage.digital_signature()) {
+        bail!(
+            "status list token signer {} does not allow digitalSignature",
+            signer.tbs_certificate.subject
+        );
+    }
+    let trusted = path.iter().any(|issuer| {
+        check_issuing_certificate(issuer).is_ok() && verify_issued_by(signer, issuer).is_ok()
+    });
+    if !trusted {
+        bail!(
+            "status list token signer {} is not trusted",
+            signer.tbs_certificate.subject
+        );
+    }
+    Ok(())
+}
+
+fn map_get<'a>(map: &'a Cbor, key: &str) -> Option<&'a Cbor> {
+    map.as_map()?
+        .iter()
+        .find(|(k, _)| k.as_text() == Some(key))
+        .map(|(_, v)| v)
+}
+
+fn claim(claims: &Cbor, key: i64) -> Option<&Cbor> {
+    claims
+        .as_map()?
+        .iter()
+        .find(|(k, _)| k.as_integer() == Some(key.into()))
+        .map(|(_, v)| v)
+}
+
+fn as_i64(value: &Cbor) -> Option<i64> {
+    value
+        .as_integer()
+        .and_then(|value| i64::try_from(value).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, sync::Arc};
+
+    use coset::{CoseSign1Builder, HeaderBuilder};
+    use flate2::{Compression, write::ZlibEncoder};
+    use p256::ecdsa::{Signature, signature::Signer};
+    use pkcs8::der::Encode;
+
+    use super::*;
+    use crate::{
+        fetch::PreloadedFetcher,
+        test_utils::{CertificateSpec, Issued, NOW, TestChain, jwt, key},
+    };
+
+    const URI: &str = "https://issuer.example/statuslists/1";
+
+    fn compress(lst: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(lst).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// A status list token listing one valid credential, cached for an hour.
+    fn status_list_jwt(alg: &str, signer: &Issued) -> String {
+        jwt(
+            &signer.key,
+            json!({
+                "alg": alg,
+                "typ": "statuslist+jwt",
+                "x5c": [BASE64_STANDARD.encode(signer.certificate.to_der().unwrap())],
+            }),
+            json!({
+                "sub": URI,
+                "ttl": 3600,
+                "status_list": { "bits": 1, "lst": BASE64_URL_SAFE_NO_PAD.encode(compress(&[0])) },
+            }),
+        )
+    }
+
+    fn status_list_cwt(typ: &str, signer: &Issued) -> Vec<u8> {
+        let claims = Cbor::Map(vec![
+            (Cbor::from(CWT_SUB), Cbor::from(URI)),
+            (
+                Cbor::from(CWT_STATUS_LIST),
+                Cbor::Map(vec![
+                    (Cbor::from("bits"), Cbor::from(1)),
+                    (Cbor::from("lst"), Cbor::Bytes(compress(&[0]))),
+                ]),
+            ),
+        ]);
+        let protected = HeaderBuilder::new()
+            .algorithm(iana::Algorithm::ES256)
+            .value(COSE_TYP, Cbor::from(typ))
+            .value(
+                X5CHAIN_COSE_HEADER_LABEL,
+                Cbor::Bytes(signer.certificate.to_der().unwrap()),
+            )
+            .build();
+        CoseSign1Builder::new()
+            .protected(protected)
+            .payload(cbor::to_vec(&claims).unwrap())
+            .create_signature(b"", |message| {
+                let signature: Signature = signer.key.sign(message);
+                signature.to_vec()
+            })
+            .build()
+            .to_tagged_vec()
+            .unwrap()
+    }
+
+    fn at(timestamp: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(timestamp).unwrap()
+    }
+
+    #[test]
+    fn reads_packed_statuses() {
+        let status_list = StatusList::new(2, &compress(&[0b1001_0100]), None).unwrap();
+        let statuses: Vec<u8> = (0..4).map(|idx| status_list.status(idx).unwrap()).collect();
+        assert_eq!(statuses, [0, 1, 1, 2]);
+        assert!(status_list.status(4).is_err());
+    }
+
+    #[test]
+    fn rejects_overflowing_index() {
+        let status_list = StatusList::new(2, &compress(&[0]), None).unwrap();
+        assert!(status_list.status(u64::MAX).is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_status_list() {
+        let lst = vec![0; MAX_STATUS_LIST_SIZE as usize + 1];
+        assert!(StatusList::new(1, &compress(&lst), None).is_err());
+    }
+
+    #[test]
+    fn accepts_token_signed_by_document_signer() {
+        let chain = TestChain::new();
+        let path = chain.path();
+        let token = decode_jwt(&status_list_jwt("ES256", &chain.ds), URI, &path, at(NOW)).unwrap();
+        assert_eq!(token.status_list.status(0).unwrap(), STATUS_VALID);
+        let token = decode_cwt(
+            &status_list_cwt("application/statuslist+cwt", &chain.ds),
+            URI,
+            &path,
+            at(NOW),
+        )
+        .unwrap();
+        assert_eq!(token.status_list.status(0).unwrap(), STATUS_VALID);
+    }
+
+    #[test]
+    fn rejects_cwt_of_another_type() {
+        let chain = TestChain::new();
+        let path = chain.path();
+        let cwt = status_list_cwt("application/cwt", &chain.ds);
+        let error = decode_cwt(&cwt, URI, &path, at(NOW)).unwrap_err();
+        assert!(format!("{error:#}").contains("unexpected status list token type"));
+    }
+
+    #[test]
+    fn rejects_algorithm_not_matching_signer_key() {
+        let chain = TestChain::new();
+        let path = chain.path();
+        let error =
+            decode_jwt(&status_list_jwt("ES384", &chain.ds), URI, &path, at(NOW)).unwrap_err();
+        assert!(format!("{error:#}").contains("does not match the public key"));
+    }
+
+    #[test]
+    fn rejects_expired_signer() {
+        let chain = TestChain::new();
+        let signer = CertificateSpec {
+            not_after: NOW - 1,
+            ..CertificateSpec::leaf("CN=Test Status List Signer")
+        }
+        .issue(key(4), Some(&chain.iaca));
+        let path = chain.path();
+        let error =
+            decode_jwt(&status_list_jwt("ES256", &signer), URI, &path, at(NOW)).unwrap_err();
+        assert!(format!("{error:#}").contains("expired or not yet valid"));
+    }
+
+    #[test]
+    fn rejects_signer_issued_by_document_signer() {
+        let chain = TestChain::new();
+        let signer =
+            CertificateSpec::leaf("CN=Test Status List Signer").issue(key(4), Some(&chain.ds));
+        let path = chain.path();
+        let error =
+            decode_jwt(&status_list_jwt("ES256", &signer), URI, &path, at(NOW)).unwrap_err();
+        assert!(format!("{error:#}").contains("is not trusted"));
+    }
+
+    #[tokio::test]
+    async fn checks_signer_of_cached_list_against_each_path() {
+        let chain = TestChain::new();
+        let other = CertificateSpec::ca("CN=Other IACA").issue(key(4), None);
+        let other_ds = CertificateSpec::leaf("CN=Other DS").issue(key(5), Some(&other));
+        let fetcher = PreloadedFetcher::new()
+            .with_resource(URI, status_list_jwt("ES256", &chain.ds).into_bytes());
+        let checker = StatusListChecker::new(Arc::new(fetcher));
+        let reference = StatusListReference {
+            idx: 0,
+            uri: URI.to_string(),
+        };
+
+        let report = checker.check(&reference, &chain.path(), at(NOW)).await;
+        assert!(report.unknown.is_empty());
+        let report = checker
+            .check(
+                &reference,
+                &[other_ds.certificate, other.certificate],
+                at(NOW),
+            )
+            .await;
+        assert_eq!(report.unknown[0].code, STATUS_LIST_UNAVAILABLE);
+    }
+}
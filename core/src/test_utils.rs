@@ -0,0 +1,313 @@
+//! Certificates, CRLs and credentials issued on the fly for tests.
+
+use std::{str::FromStr, time::Duration};
+
+use base64::prelude::*;
+use isomdl::definitions::x509::trust_anchor::{TrustAnchor, TrustAnchorRegistry, TrustPurpose};
+use p256::ecdsa::{DerSignature, Signature, SigningKey, signature::Signer};
+use pkcs8::der::{
+    Encode,
+    asn1::{BitString, GeneralizedTime, Ia5String},
+};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use x509_cert::{
+    Certificate, Version,
+    builder::{Builder, CertificateBuilder, Profile},
+    crl::{CertificateList, RevokedCert, TbsCertList},
+    ext::pkix::{
+        CrlDistributionPoints, SubjectAltName,
+        crl::dp::DistributionPoint,
+        name::{DistributionPointName, GeneralName},
+    },
+    name::Name,
+    serial_number::SerialNumber,
+    spki::{AlgorithmIdentifierOwned, ObjectIdentifier, SubjectPublicKeyInfoOwned},
+    time::{Time, Validity},
+};
+
+const ECDSA_WITH_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+
+/// 2025-01-01T00:00:00Z, around which test certificates are valid.
+pub(crate) const NOW: i64 = 1_735_689_600;
+const DAY: i64 = 86_400;
+
+/// The `iss` of the credentials issued by [TestChain], which its document
+/// signer names.
+pub(crate) const ISS: &str = "https://issuer.example";
+/// The `vct` of the SD-JWT VCs issued by [TestChain].
+pub(crate) const VCT: &str = "urn:eudi:pid:1";
+/// The nonce and audience [TestChain] presentations are bound to.
+pub(crate) const NONCE: &str = "nonce";
+pub(crate) const AUD: &str = "x509_san_dns:rp.example.com";
+
+/// A deterministic P-256 key.
+pub(crate) fn key(seed: u8) -> SigningKey {
+    SigningKey::from_slice(&[seed; 32]).unwrap()
+}
+
+pub(crate) fn time(timestamp: i64) -> Time {
+    Time::GeneralTime(
+        GeneralizedTime::from_unix_duration(Duration::from_secs(timestamp as u64)).unwrap(),
+    )
+}
+
+/// A certificate and the key it certifies.
+pub(crate) struct Issued {
+    pub certificate: Certificate,
+    pub key: SigningKey,
+}
+
+/// How a test certificate is issued.
+pub(crate) struct CertificateSpec<'a> {
+    pub subject: &'a str,
+    pub serial: u32,
+    /// Whether the certificate is a CA, with `cA` and `keyCertSign`.
+    pub ca: bool,
+    pub not_before: i64,
+    pub not_after: i64,
+    pub crl_url: Option<&'a str>,
+    pub san_uri: Option<&'a str>,
+}
+
+impl<'a> CertificateSpec<'a> {
+    pub(crate) fn ca(subject: &'a str) -> Self {
+        Self {
+            subject,
+            serial: 1,
+            ca: true,
+            not_before: NOW - DAY,
+            not_after: NOW + 365 * DAY,
+            crl_url: None,
+            san_uri: None,
+        }
+    }
+
+    pub(crate) fn leaf(subject: &'a str) -> Self {
+        Self {
+            ca: false,
+            serial: 2,
+            ..Self::ca(subject)
+        }
+    }
+
+    /// Issues the certificate for `key`, signed by `issuer` or self-signed.
+    pub(crate) fn issue(&self, key: SigningKey, issuer: Option<&Issued>) -> Issued {
+        let subject = Name::from_str(self.subject).unwrap();
+        let profile = match (issuer, self.ca) {
+            (None, _) => Profile::Root,
+            (Some(issuer), true) => Profile::SubCA {
+                issuer: issuer.certificate.tbs_certificate.subject.clone(),
+                path_len_constraint: None,
+            },
+            (Some(issuer), false) => Profile::Leaf {
+                issuer: issuer.certificate.tbs_certificate.subject.clone(),
+                enable_key_agreement: false,
+                enable_key_encipherment: false,
+                include_subject_key_identifier: true,
+            },
+        };
+        let validity = Validity {
+            not_before: time(self.not_before),
+            not_after: time(self.not_after),
+        };
+        let public_key = SubjectPublicKeyInfoOwned::from_key(*key.verifying_key()).unwrap();
+        let signer = issuer.map_or(&key, |issuer| &issuer.key);
+        let mut builder = CertificateBuilder::new(
+            profile,
+            SerialNumber::from(self.serial),
+            validity,
+            subject,
+            public_key,
+            signer,
+        )
+        .unwrap();
+        if let Some(url) = self.crl_url {
+            builder
+                .add_extension(&CrlDistributionPoints(vec![DistributionPoint {
+                    distribution_point: Some(DistributionPointName::FullName(vec![
+                        GeneralName::UniformResourceIdentifier(Ia5String::new(url).unwrap()),
+                    ])),
+                    reasons: None,
+                    crl_issuer: None,
+                }]))
+                .unwrap();
+        }
+        if let Some(uri) = self.san_uri {
+            builder
+                .add_extension(&SubjectAltName(vec![
+                    GeneralName::UniformResourceIdentifier(Ia5String::new(uri).unwrap()),
+                ]))
+                .unwrap();
+        }
+        Issued {
+            certificate: builder.build::<DerSignature>().unwrap(),
+            key,
+        }
+    }
+}
+
+/// An IACA, the document signer it issues, and the holder key credentials
+/// are bound to.
+pub(crate) struct TestChain {
+    pub iaca: Issued,
+    pub ds: Issued,
+    pub holder: SigningKey,
+}
+
+impl TestChain {
+    /// `CN=Test IACA`, issuing `CN=Test DS` named by the URI [ISS].
+    pub(crate) fn new() -> Self {
+        Self::issue(
+            CertificateSpec::ca("CN=Test IACA"),
+            CertificateSpec {
+                san_uri: Some(ISS),
+                ..CertificateSpec::leaf("CN=Test DS")
+            },
+        )
+    }
+
+    /// Issues the IACA and document signer from their specs.
+    pub(crate) fn issue(iaca: CertificateSpec, ds: CertificateSpec) -> Self {
+        let iaca = iaca.issue(key(1), None);
+        let ds = ds.issue(key(2), Some(&iaca));
+        Self {
+            iaca,
+            ds,
+            holder: key(3),
+        }
+    }
+
+    /// The certification path, from the document signer to the IACA.
+    pub(crate) fn path(&self) -> [Certificate; 2] {
+        [self.ds.certificate.clone(), self.iaca.certificate.clone()]
+    }
+
+    /// A registry trusting the IACA.
+    pub(crate) fn registry(&self) -> TrustAnchorRegistry {
+        TrustAnchorRegistry {
+            anchors: vec![TrustAnchor {
+                certificate: self.iaca.certificate.clone(),
+                purpose: TrustPurpose::Iaca,
+            }],
+        }
+    }
+
+    /// The claims of an SD-JWT VC of [VCT] bound to the holder key, valid for a
+    /// day and referencing `disclosures`.
+    pub(crate) fn sd_jwt_claims(&self, disclosures: &[String]) -> Value {
+        json!({
+            "iss": ISS,
+            "vct": VCT,
+            "iat": NOW,
+            "exp": NOW + DAY,
+            "cnf": { "jwk": jwk(&self.holder) },
+            "_sd": disclosures.iter().map(|d| digest(d)).collect::<Vec<_>>(),
+        })
+    }
+
+    /// An issuer-signed SD-JWT VC of `claims`, signed by the document signer
+    /// with `alg` and carrying `x5c`.
+    pub(crate) fn sd_jwt(&self, alg: &str, x5c: &[&Issued], claims: Value) -> String {
+        let x5c: Vec<String> = x5c
+            .iter()
+            .map(|issued| BASE64_STANDARD.encode(issued.certificate.to_der().unwrap()))
+            .collect();
+        jwt(
+            &self.ds.key,
+            json!({ "alg": alg, "typ": "dc+sd-jwt", "x5c": x5c }),
+            claims,
+        )
+    }
+
+    /// A verifiable presentation of `disclosures`, for [NONCE] and [AUD].
+    pub(crate) fn present(&self, disclosures: &[String]) -> String {
+        let sd_jwt = self.sd_jwt("ES256", &[&self.ds], self.sd_jwt_claims(disclosures));
+        sd_jwt_presentation(&sd_jwt, disclosures, &self.holder, NONCE, AUD)
+    }
+}
+
+/// A DER encoded CRL issued by `issuer`, listing the `revoked` serial numbers.
+pub(crate) fn crl(issuer: &Issued, revoked: &[u32], this_update: i64, next_update: i64) -> Vec<u8> {
+    let algorithm = AlgorithmIdentifierOwned {
+        oid: ECDSA_WITH_SHA256,
+        parameters: None,
+    };
+    let tbs_cert_list = TbsCertList {
+        version: Version::V2,
+        signature: algorithm.clone(),
+        issuer: issuer.certificate.tbs_certificate.subject.clone(),
+        this_update: time(this_update),
+        next_update: Some(time(next_update)),
+        revoked_certificates: (!revoked.is_empty()).then(|| {
+            revoked
+                .iter()
+                .map(|serial| RevokedCert {
+                    serial_number: SerialNumber::from(*serial),
+                    revocation_date: time(this_update),
+                    crl_entry_extensions: None,
+                })
+                .collect()
+        }),
+        crl_extensions: None,
+    };
+    let signature: DerSignature = issuer.key.sign(&tbs_cert_list.to_der().unwrap());
+    CertificateList {
+        tbs_cert_list,
+        signature_algorithm: algorithm,
+        signature: BitString::from_bytes(signature.as_bytes()).unwrap(),
+    }
+    .to_der()
+    .unwrap()
+}
+
+/// Signs a compact JWS of `header` and `payload` with the fixed-size ES256 `key`.
+pub(crate) fn jwt(key: &SigningKey, header: Value, payload: Value) -> String {
+    let message = format!(
+        "{}.{}",
+        BASE64_URL_SAFE_NO_PAD.encode(header.to_string()),
+        BASE64_URL_SAFE_NO_PAD.encode(payload.to_string())
+    );
+    let signature: Signature = key.sign(message.as_bytes());
+    format!(
+        "{message}.{}",
+        BASE64_URL_SAFE_NO_PAD.encode(signature.to_bytes())
+    )
+}
+
+/// An SD-JWT disclosure of the claim `name`.
+pub(crate) fn disclosure(name: &str, value: Value) -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(json!(["salt", name, value]).to_string())
+}
+
+/// The SD-JWT digest of `disclosure`.
+pub(crate) fn digest(disclosure: &str) -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(disclosure))
+}
+
+/// The public JWK of `key`.
+pub(crate) fn jwk(key: &SigningKey) -> Value {
+    serde_json::from_str(&p256::PublicKey::from(key.verifying_key()).to_jwk_string()).unwrap()
+}
+
+/// An SD-JWT VC presentation of `issuer_jwt` with `disclosures`, whose key
+/// binding JWT, signed by `holder`, is for `nonce` and `aud`.
+pub(crate) fn sd_jwt_presentation(
+    issuer_jwt: &str,
+    disclosures: &[String],
+    holder: &SigningKey,
+    nonce: &str,
+    aud: &str,
+) -> String {
+    let presented: String = disclosures
+        .iter()
+        .fold(format!("{issuer_jwt}~"), |presented, disclosure| {
+            format!("{presented}{disclosure}~")
+        });
+    let key_binding = jwt(
+        holder,
+        json!({ "alg": "ES256", "typ": "kb+jwt" }),
+        json!({ "iat": NOW, "aud": aud, "nonce": nonce, "sd_hash": digest(&presented) }),
+    );
+    format!("{presented}{key_binding}")
+}
@@ -1,7 +1,12 @@
 use isomdl::definitions::helpers::{NonEmptyMap, NonEmptyVec};
 use serde::{Deserialize, Serialize};
 
-use crate::{annex_c, annex_d};
+use crate::{
+    annex_c, annex_d,
+    rules::RulePolicy,
+    schema::{EU_PID_DOC_TYPE, EU_PID_NAMESPACE, MDL_DOC_TYPE},
+    sd_jwt::SdJwtVcRequest,
+};
 
 // Note this is also referred to as `Annex C`,
 // in reference to ISO/IEC 18013-7
@@ -44,6 +49,33 @@ pub enum DCAPIRequestType {
 pub struct DCAPINamespaceRequest {
     pub namespaces: NonEmptyMap<String, NonEmptyVec<String>>,
     pub origin: String,
+    /// The doctype of the requested credential, see [DCAPINamespaceRequest::doc_type].
+    #[serde(default)]
+    pub doc_type: Option<String>,
+    /// Rules the response to this request must satisfy.
+    #[serde(default)]
+    pub policy: Option<RulePolicy>,
+    /// An SD-JWT VC accepted instead of the mdoc, over OpenID4VP only.
+    #[serde(default)]
+    pub sd_jwt: Option<SdJwtVcRequest>,
+}
+
+impl DCAPINamespaceRequest {
+    /// The requested doctype. Defaults to the EU PID if only its namespace is
+    /// requested, and to the mDL otherwise.
+    pub fn doc_type(&self) -> &str {
+        match &self.doc_type {
+            Some(doc_type) => doc_type,
+            None if self
+                .namespaces
+                .keys()
+                .all(|namespace| namespace == EU_PID_NAMESPACE) =>
+            {
+                EU_PID_DOC_TYPE
+            }
+            None => MDL_DOC_TYPE,
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize)]
@@ -0,0 +1,219 @@
+use anyhow::{Result, bail};
+use isomdl::definitions::validity_info::ValidityInfo;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use time::{Duration, OffsetDateTime};
+use x509_cert::Certificate;
+
+use crate::verdict::VerdictReason;
+
+pub const DEFAULT_CLOCK_SKEW_SECONDS: i64 = 300;
+/// The largest accepted [ValidityPolicy::clock_skew_seconds], one day.
+pub const MAX_CLOCK_SKEW_SECONDS: i64 = 86_400;
+
+/// Code of the [VerdictReason]s reporting a credential outside of its validity period.
+pub const MSO_NOT_YET_VALID: &str = "mso_not_yet_valid";
+pub const MSO_EXPIRED: &str = "mso_expired";
+/// Code of the [VerdictReason]s reporting a stale credential.
+pub const MSO_SIGNED_IN_FUTURE: &str = "mso_signed_in_future";
+pub const MSO_TOO_OLD: &str = "mso_too_old";
+pub const MSO_UPDATE_OVERDUE: &str = "mso_update_overdue";
+
+/// Checks on the `validityInfo` of the mdoc Mobile Security Object.
+///
+/// `validFrom` and `validUntil` are always enforced.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidityPolicy {
+    /// Tolerance applied to every comparison with the current time.
+    #[serde(default = "default_clock_skew_seconds")]
+    pub clock_skew_seconds: i64,
+    /// Reject credentials signed longer ago than this.
+    #[serde(default)]
+    pub max_credential_age_seconds: Option<i64>,
+    /// Reject credentials past their `expectedUpdate`.
+    #[serde(default)]
+    pub enforce_expected_update: bool,
+}
+
+fn default_clock_skew_seconds() -> i64 {
+    DEFAULT_CLOCK_SKEW_SECONDS
+}
+
+impl Default for ValidityPolicy {
+    fn default() -> Self {
+        Self {
+            clock_skew_seconds: DEFAULT_CLOCK_SKEW_SECONDS,
+            max_credential_age_seconds: None,
+            enforce_expected_update: false,
+        }
+    }
+}
+
+/// A failed [ValidityPolicy] check.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidityFailure {
+    pub code: &'static str,
+    pub detail: serde_json::Value,
+}
+
+impl ValidityFailure {
+    /// Whether the credential is outside of its validity period, as opposed to stale.
+    pub fn is_expiry(&self) -> bool {
+        matches!(self.code, MSO_NOT_YET_VALID | MSO_EXPIRED)
+    }
+}
+
+impl From<ValidityFailure> for VerdictReason {
+    fn from(failure: ValidityFailure) -> Self {
+        VerdictReason {
+            code: failure.code.to_string(),
+            detail: failure.detail,
+        }
+    }
+}
+
+/// Checks that `certificate` is within its validity period as of `now`.
+pub fn check_certificate_validity(certificate: &Certificate, now: OffsetDateTime) -> Result<()> {
+    let validity = &certificate.tbs_certificate.validity;
+    let now = now.unix_timestamp();
+    let not_before = i64::try_from(validity.not_before.to_unix_duration().as_secs())?;
+    let not_after = i64::try_from(validity.not_after.to_unix_duration().as_secs())?;
+    if now < not_before || now > not_after {
+        bail!(
+            "certificate {} is expired or not yet valid",
+            certificate.tbs_certificate.subject
+        );
+    }
+    Ok(())
+}
+
+impl ValidityPolicy {
+    /// Checks that the clock skew is within `0..=MAX_CLOCK_SKEW_SECONDS`, and
+    /// that the maximum credential age is not negative.
+    pub fn validate(&self) -> Result<()> {
+        if !(0..=MAX_CLOCK_SKEW_SECONDS).contains(&self.clock_skew_seconds) {
+            bail!(
+                "clockSkewSeconds must be between 0 and {MAX_CLOCK_SKEW_SECONDS}, got {}",
+                self.clock_skew_seconds
+            );
+        }
+        if let Some(max_age) = self.max_credential_age_seconds
+            && max_age < 0
+        {
+            bail!("maxCredentialAgeSeconds must not be negative, got {max_age}");
+        }
+        Ok(())
+    }
+
+    /// Checks `validity` as of `now`. Bounds that overflow the range of dates
+    /// never fail.
+    pub fn check(&self, validity: &ValidityInfo, now: OffsetDateTime) -> Vec<ValidityFailure> {
+        let skew = Duration::seconds(self.clock_skew_seconds);
+        let latest = now.checked_add(skew);
+        let earliest = now.checked_sub(skew);
+        let mut failures = Vec::new();
+        let mut fail = |code, field: &str, value: OffsetDateTime| {
+            failures.push(ValidityFailure {
+                code,
+                detail: json!({
+                    field: value.unix_timestamp(),
+                    "now": now.unix_timestamp(),
+                }),
+            })
+        };
+
+        if latest.is_some_and(|latest| validity.valid_from > latest) {
+            fail(MSO_NOT_YET_VALID, "validFrom", validity.valid_from);
+        }
+        if earliest.is_some_and(|earliest| validity.valid_until < earliest) {
+            fail(MSO_EXPIRED, "validUntil", validity.valid_until);
+        }
+        if latest.is_some_and(|latest| validity.signed > latest) {
+            fail(MSO_SIGNED_IN_FUTURE, "signed", validity.signed);
+        }
+        if let Some(max_age) = self.max_credential_age_seconds
+            && let Some(earliest) = earliest
+            && validity
+                .signed
+                .checked_add(Duration::seconds(max_age))
+                .is_some_and(|oldest| oldest < earliest)
+        {
+            fail(MSO_TOO_OLD, "signed", validity.signed);
+        }
+        if self.enforce_expected_update
+            && let Some(expected_update) = validity.expected_update
+            && earliest.is_some_and(|earliest| expected_update < earliest)
+        {
+            fail(MSO_UPDATE_OVERDUE, "expectedUpdate", expected_update);
+        }
+
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(timestamp: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(timestamp).unwrap()
+    }
+
+    fn validity() -> ValidityInfo {
+        ValidityInfo {
+            signed: at(1_735_689_600),
+            valid_from: at(1_735_689_600),
+            valid_until: at(1_767_225_600),
+            expected_update: None,
+        }
+    }
+
+    #[test]
+    fn does_not_overflow_at_the_bounds_of_dates() {
+        let policy = ValidityPolicy {
+            clock_skew_seconds: i64::MAX,
+            max_credential_age_seconds: Some(i64::MAX),
+            enforce_expected_update: false,
+        };
+        assert!(
+            policy
+                .check(&validity(), OffsetDateTime::now_utc())
+                .is_empty()
+        );
+        let policy = ValidityPolicy {
+            max_credential_age_seconds: Some(i64::MAX),
+            ..Default::default()
+        };
+        let now = at(1_748_736_000);
+        assert!(policy.check(&validity(), now).is_empty());
+    }
+
+    #[test]
+    fn rejects_out_of_range_policies() {
+        assert!(ValidityPolicy::default().validate().is_ok());
+        let policy = ValidityPolicy {
+            clock_skew_seconds: -1,
+            ..Default::default()
+        };
+        assert!(policy.validate().is_err());
+        let policy = ValidityPolicy {
+            clock_skew_seconds: MAX_CLOCK_SKEW_SECONDS + 1,
+            ..Default::default()
+        };
+        assert!(policy.validate().is_err());
+        let policy = ValidityPolicy {
+            max_credential_age_seconds: Some(-1),
+            ..Default::default()
+        };
+        assert!(policy.validate().is_err());
+    }
+
+    #[test]
+    fn reports_expired_credentials() {
+        let failures = ValidityPolicy::default().check(&validity(), at(1_769_904_000));
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].code, MSO_EXPIRED);
+        assert!(failures[0].is_expiry());
+    }
+}
@@ -0,0 +1,523 @@
+use isomdl::{
+    definitions::validity_info::ValidityInfo,
+    presentation::authentication::{AuthenticationStatus, ResponseAuthenticationOutcome},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use time::OffsetDateTime;
+
+use crate::{
+    aamva::AamvaExtensions,
+    disclosure::{DisclosureReport, RequestedElements},
+    eu_pid::EuPid,
+    mdl::MobileDrivingLicence,
+    policy::VerificationPolicy,
+    rules::RulePolicy,
+    schema::{INVALID_ELEMENT, IssuerElements, element_to_json},
+    validity::ValidityPolicy,
+};
+
+/// Key of the [ResponseAuthenticationOutcome] errors reporting parsing failures.
+pub const PARSING_ERRORS: &str = "parsing_errors";
+
+/// Keys of the [ResponseAuthenticationOutcome] errors reporting issuer and
+/// device authentication failures.
+pub const ISSUER_AUTHENTICATION_ERRORS: &str = "issuer_authentication_errors";
+pub const DEVICE_AUTHENTICATION_ERRORS: &str = "device_authentication_errors";
+
+/// Key of the [ResponseAuthenticationOutcome] errors reporting a credential
+/// outside of its validity period.
+pub const VALIDITY_ERRORS: &str = "validity_errors";
+
+/// Key of the [ResponseAuthenticationOutcome] errors reporting a certificate
+/// outside of its validity period.
+pub const CERTIFICATE_VALIDITY_ERRORS: &str = "certificate_validity_errors";
+
+/// Code of the [VerdictReason] reporting elements returned but not requested.
+pub const OVER_DISCLOSURE: &str = "over_disclosure";
+
+/// A single reason behind a [Verdict], taken from the verification errors.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VerdictReason {
+    /// The verification step that reported the error, e.g. `parsing_errors`.
+    pub code: String,
+    pub detail: serde_json::Value,
+}
+
+/// The overall result of verifying a response.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Verdict {
+    Verified,
+    /// The response could not be parsed.
+    ParseFailed {
+        reasons: Vec<VerdictReason>,
+    },
+    /// The issuer signature is invalid or does not chain to a trust anchor.
+    IssuerUntrusted {
+        reasons: Vec<VerdictReason>,
+    },
+    /// The device signature or MAC is invalid, e.g. due to a transcript mismatch.
+    DeviceAuthFailed {
+        reasons: Vec<VerdictReason>,
+    },
+    /// A certificate or the credential is outside of its validity period.
+    Expired {
+        reasons: Vec<VerdictReason>,
+    },
+    /// The credential or an issuer certificate has been revoked.
+    Revoked {
+        reasons: Vec<VerdictReason>,
+    },
+    /// The credential has been suspended by its issuer.
+    Suspended {
+        reasons: Vec<VerdictReason>,
+    },
+    /// The response is authentic but rejected by the [VerificationPolicy].
+    PolicyViolation {
+        reasons: Vec<VerdictReason>,
+    },
+}
+
+impl Verdict {
+    /// Classifies the outcome on the keys of its errors. Errors under a key
+    /// other than those of parsing, validity and device authentication are
+    /// taken as issuer failures.
+    pub fn from_outcome(outcome: &ResponseAuthenticationOutcome) -> Self {
+        let reasons: Vec<VerdictReason> = outcome
+            .errors
+            .iter()
+            .map(|(code, detail)| VerdictReason {
+                code: code.clone(),
+                detail: detail.clone(),
+            })
+            .collect();
+
+        if outcome.errors.contains_key(PARSING_ERRORS) {
+            return Verdict::ParseFailed { reasons };
+        }
+        let (expired, reasons): (Vec<_>, Vec<_>) =
+            reasons.into_iter().partition(VerdictReason::is_expiry);
+        if !expired.is_empty() {
+            return Verdict::Expired { reasons: expired };
+        }
+        let (device, issuer): (Vec<_>, Vec<_>) = reasons
+            .into_iter()
+            .partition(|reason| reason.code == DEVICE_AUTHENTICATION_ERRORS);
+        if !issuer.is_empty() {
+            return Verdict::IssuerUntrusted { reasons: issuer };
+        }
+        if !matches!(outcome.issuer_authentication, AuthenticationStatus::Valid) {
+            return Verdict::IssuerUntrusted { reasons: device };
+        }
+        if !device.is_empty()
+            || !matches!(outcome.device_authentication, AuthenticationStatus::Valid)
+        {
+            return Verdict::DeviceAuthFailed { reasons: device };
+        }
+        Verdict::Verified
+    }
+
+    pub fn is_verified(&self) -> bool {
+        matches!(self, Verdict::Verified)
+    }
+
+    /// The `kind` of the verdict, e.g. `issuer_untrusted`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Verdict::Verified => "verified",
+            Verdict::ParseFailed { .. } => "parse_failed",
+            Verdict::IssuerUntrusted { .. } => "issuer_untrusted",
+            Verdict::DeviceAuthFailed { .. } => "device_auth_failed",
+            Verdict::Expired { .. } => "expired",
+            Verdict::Revoked { .. } => "revoked",
+            Verdict::Suspended { .. } => "suspended",
+            Verdict::PolicyViolation { .. } => "policy_violation",
+        }
+    }
+}
+
+impl VerdictReason {
+    fn is_expiry(&self) -> bool {
+        self.code == VALIDITY_ERRORS || self.code == CERTIFICATE_VALIDITY_ERRORS
+    }
+}
+
+/// The outcome of revocation checks.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RevocationReport {
+    pub revoked: Vec<VerdictReason>,
+    pub suspended: Vec<VerdictReason>,
+    /// Checks that could not be completed, e.g. as a CRL could not be fetched.
+    pub unknown: Vec<VerdictReason>,
+}
+
+impl RevocationReport {
+    pub fn extend(&mut self, other: RevocationReport) {
+        self.revoked.extend(other.revoked);
+        self.suspended.extend(other.suspended);
+        self.unknown.extend(other.unknown);
+    }
+}
+
+/// A verified response: its [Verdict], and the outcome it was derived from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VerificationResult {
+    pub verdict: Verdict,
+    pub outcome: ResponseAuthenticationOutcome,
+    /// How the returned elements compare to the requested ones.
+    #[serde(default)]
+    pub disclosure: DisclosureReport,
+    /// Returned elements whose value does not match the schema of their namespace.
+    #[serde(default)]
+    pub schema_violations: Vec<VerdictReason>,
+    /// The returned mDL elements, typed.
+    #[serde(default)]
+    pub mdl: Option<MobileDrivingLicence>,
+    /// The returned AAMVA elements, typed.
+    #[serde(default)]
+    pub aamva: Option<AamvaExtensions>,
+    /// The returned EU PID elements, typed.
+    #[serde(default)]
+    pub eu_pid: Option<EuPid>,
+    /// The result of each presentation, when a response carries several. The
+    /// other fields are then those of the first credential set option whose
+    /// presentations are all verified or, if there is none, of the first
+    /// presentation that failed verification.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub presentations: Vec<PresentationResult>,
+}
+
+/// The verification of one presentation of a response, returned for the
+/// credential query `credential_id`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PresentationResult {
+    pub credential_id: String,
+    pub result: VerificationResult,
+}
+
+impl VerificationResult {
+    /// The result of a credential that could not be decoded.
+    pub fn parse_failed(error: impl std::fmt::Display) -> Self {
+        let mut outcome = ResponseAuthenticationOutcome::default();
+        outcome
+            .errors
+            .insert(PARSING_ERRORS.to_string(), json!(vec![error.to_string()]));
+        outcome.into()
+    }
+
+    /// Compares the response to the `requested` elements, rejecting an
+    /// otherwise verified response that over-discloses under a strict `policy`.
+    ///
+    /// Nothing is reported when the response could not be parsed, or for
+    /// sessions initiated without recording the requested elements.
+    pub fn check_disclosure(&mut self, requested: &RequestedElements, policy: &VerificationPolicy) {
+        if requested.is_empty() || matches!(self.verdict, Verdict::ParseFailed { .. }) {
+            return;
+        }
+        self.disclosure = DisclosureReport::new(requested, &self.outcome.response);
+
+        if policy.strict_disclosure && self.disclosure.is_over_disclosed() {
+            self.reject(VerdictReason {
+                code: OVER_DISCLOSURE.to_string(),
+                detail: json!(self.disclosure.over_disclosed),
+            });
+        }
+    }
+
+    /// Validates the returned elements against the schema of their namespace,
+    /// replacing their values with the typed JSON converted from the
+    /// issuer-signed `elements`. Under a strict `policy`, an otherwise verified
+    /// response with invalid elements is rejected.
+    pub fn check_schema(&mut self, elements: &IssuerElements, policy: &VerificationPolicy) {
+        if matches!(self.verdict, Verdict::ParseFailed { .. }) {
+            return;
+        }
+        for (namespace, values) in elements {
+            let Some(serde_json::Value::Object(returned)) =
+                self.outcome.response.get_mut(namespace)
+            else {
+                continue;
+            };
+            for (element, value) in values {
+                let Some(returned) = returned.get_mut(element) else {
+                    continue;
+                };
+                match element_to_json(namespace, element, value, policy.strict_schema) {
+                    Ok(value) => *returned = value,
+                    Err(error) => self.schema_violations.push(VerdictReason {
+                        code: INVALID_ELEMENT.to_string(),
+                        detail: json!({
+                            "namespace": namespace,
+                            "element": element,
+                            "error": error,
+                        }),
+                    }),
+                }
+            }
+        }
+
+        if policy.strict_schema {
+            for violation in self.schema_violations.clone() {
+                self.reject(violation);
+            }
+        }
+    }
+
+    /// Evaluates the declarative `rules` attached to the request against the
+    /// returned elements, rejecting the response with a reason per failed rule.
+    pub fn check_rules(&mut self, rules: &RulePolicy, now: OffsetDateTime) {
+        if rules.is_empty() || matches!(self.verdict, Verdict::ParseFailed { .. }) {
+            return;
+        }
+        for failure in rules.evaluate(&self.outcome.response, now) {
+            self.reject(failure.into());
+        }
+    }
+
+    /// Applies the outcome of revocation checks. Unless the `policy` allows it,
+    /// a response whose revocation status is unknown is rejected.
+    ///
+    /// A revoked credential takes precedence over a suspended one.
+    pub fn check_revocation(&mut self, report: RevocationReport, policy: &VerificationPolicy) {
+        for reason in report.revoked {
+            match &mut self.verdict {
+                Verdict::Verified
+                | Verdict::PolicyViolation { .. }
+                | Verdict::Expired { .. }
+                | Verdict::Suspended { .. } => {
+                    self.verdict = Verdict::Revoked {
+                        reasons: vec![reason],
+                    }
+                }
+                Verdict::Revoked { reasons } => reasons.push(reason),
+                _ => {}
+            }
+        }
+        for reason in report.suspended {
+            match &mut self.verdict {
+                Verdict::Verified | Verdict::PolicyViolation { .. } | Verdict::Expired { .. } => {
+                    self.verdict = Verdict::Suspended {
+                        reasons: vec![reason],
+                    }
+                }
+                Verdict::Suspended { reasons } => reasons.push(reason),
+                _ => {}
+            }
+        }
+        if policy.soft_fail_revocation {
+            for reason in report.unknown {
+                tracing::warn!("Revocation status unknown: {}", reason.detail);
+            }
+        } else {
+            for reason in report.unknown {
+                self.reject(reason);
+            }
+        }
+    }
+
+    /// Applies the validity `policy` to the `validityInfo` of the credential.
+    ///
+    /// A credential outside of its validity period is [Verdict::Expired],
+    /// a stale one is rejected as a [Verdict::PolicyViolation].
+    pub fn check_validity(
+        &mut self,
+        validity: &ValidityInfo,
+        policy: &ValidityPolicy,
+        now: OffsetDateTime,
+    ) {
+        for failure in policy.check(validity, now) {
+            if failure.is_expiry() {
+                self.expire(failure.into());
+            } else {
+                self.reject(failure.into());
+            }
+        }
+    }
+
+    /// Turns a verified or rejected result into a [Verdict::Expired], or adds
+    /// to the reasons of an existing one. Other failures take precedence.
+    fn expire(&mut self, reason: VerdictReason) {
+        match &mut self.verdict {
+            Verdict::Verified | Verdict::PolicyViolation { .. } => {
+                self.verdict = Verdict::Expired {
+                    reasons: vec![reason],
+                }
+            }
+            Verdict::Expired { reasons } => reasons.push(reason),
+            _ => {}
+        }
+    }
+
+    /// Turns a verified result into a [Verdict::PolicyViolation], or adds to
+    /// the reasons of an existing one. Other failures take precedence.
+    pub fn reject(&mut self, reason: VerdictReason) {
+        match &mut self.verdict {
+            Verdict::Verified => {
+                self.verdict = Verdict::PolicyViolation {
+                    reasons: vec![reason],
+                }
+            }
+            Verdict::PolicyViolation { reasons } => reasons.push(reason),
+            _ => {}
+        }
+    }
+
+    /// Combines the results of several presentations, see
+    /// [VerificationResult::presentations]. A single result is returned as is.
+    ///
+    /// `options` are the alternative sets of credential query ids of the DCQL
+    /// credential set: the response is verified if every presentation of any
+    /// one option is.
+    pub fn combine(
+        mut presentations: Vec<PresentationResult>,
+        options: &[Vec<&str>],
+    ) -> Option<Self> {
+        if presentations.len() <= 1 {
+            return presentations.pop().map(|presentation| presentation.result);
+        }
+        let verified = |credential_id: &str| {
+            presentations.iter().any(|presentation| {
+                presentation.credential_id == credential_id
+                    && presentation.result.verdict.is_verified()
+            })
+        };
+        let primary = match options
+            .iter()
+            .find(|option| !option.is_empty() && option.iter().all(|id| verified(id)))
+        {
+            Some(option) => presentations
+                .iter()
+                .find(|presentation| presentation.credential_id == option[0]),
+            None => presentations
+                .iter()
+                .find(|presentation| !presentation.result.verdict.is_verified()),
+        }
+        .unwrap_or(&presentations[0]);
+        let mut result = primary.result.clone();
+        result.presentations = presentations;
+        Some(result)
+    }
+}
+
+impl From<ResponseAuthenticationOutcome> for VerificationResult {
+    fn from(outcome: ResponseAuthenticationOutcome) -> Self {
+        Self {
+            verdict: Verdict::from_outcome(&outcome),
+            outcome,
+            disclosure: DisclosureReport::default(),
+            schema_violations: Vec::new(),
+            mdl: None,
+            aamva: None,
+            eu_pid: None,
+            presentations: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(errors: &[(&str, &str)]) -> ResponseAuthenticationOutcome {
+        let mut outcome = ResponseAuthenticationOutcome {
+            issuer_authentication: AuthenticationStatus::Valid,
+            device_authentication: AuthenticationStatus::Valid,
+            ..Default::default()
+        };
+        for (code, detail) in errors {
+            outcome
+                .errors
+                .insert(code.to_string(), json!(vec![detail.to_string()]));
+        }
+        outcome
+    }
+
+    #[test]
+    fn classifies_on_error_keys() {
+        let cases = [
+            (PARSING_ERRORS, "parse_failed"),
+            (VALIDITY_ERRORS, "expired"),
+            (CERTIFICATE_VALIDITY_ERRORS, "expired"),
+            (ISSUER_AUTHENTICATION_ERRORS, "issuer_untrusted"),
+            (DEVICE_AUTHENTICATION_ERRORS, "device_auth_failed"),
+            ("certificate_errors", "issuer_untrusted"),
+        ];
+        for (code, expected) in cases {
+            let verdict = Verdict::from_outcome(&outcome(&[(code, "error")]));
+            assert_eq!(verdict.code(), expected, "{code}");
+        }
+        assert!(Verdict::from_outcome(&outcome(&[])).is_verified());
+    }
+
+    #[test]
+    fn does_not_classify_on_error_details() {
+        let verdict = Verdict::from_outcome(&outcome(&[(
+            ISSUER_AUTHENTICATION_ERRORS,
+            "x5c certificate 1 is expired or not yet valid",
+        )]));
+        assert_eq!(verdict.code(), "issuer_untrusted");
+    }
+
+    #[test]
+    fn issuer_failures_take_precedence_over_device_failures() {
+        let verdict = Verdict::from_outcome(&outcome(&[
+            ("unknown_errors", "error"),
+            (DEVICE_AUTHENTICATION_ERRORS, "error"),
+        ]));
+        assert_eq!(
+            verdict,
+            Verdict::IssuerUntrusted {
+                reasons: vec![VerdictReason {
+                    code: "unknown_errors".to_string(),
+                    detail: json!(["error"]),
+                }]
+            }
+        );
+    }
+
+    #[test]
+    fn reports_unchecked_authentication() {
+        let mut unchecked = outcome(&[]);
+        unchecked.device_authentication = AuthenticationStatus::Unchecked;
+        assert_eq!(
+            Verdict::from_outcome(&unchecked).code(),
+            "device_auth_failed"
+        );
+        unchecked.issuer_authentication = AuthenticationStatus::Unchecked;
+        assert_eq!(Verdict::from_outcome(&unchecked).code(), "issuer_untrusted");
+    }
+
+    #[test]
+    fn reports_decode_errors_as_parse_failures() {
+        let result = VerificationResult::parse_failed("Could not decode response");
+        assert_eq!(result.verdict.code(), "parse_failed");
+    }
+
+    #[test]
+    fn combines_presentations_per_credential_set_option() {
+        let presentations = vec![
+            PresentationResult {
+                credential_id: "0".to_string(),
+                result: VerificationResult::from(outcome(&[(
+                    ISSUER_AUTHENTICATION_ERRORS,
+                    "error",
+                )])),
+            },
+            PresentationResult {
+                credential_id: "1".to_string(),
+                result: VerificationResult::from(outcome(&[])),
+            },
+        ];
+
+        // Alternatives: the verified presentation is enough.
+        let result =
+            VerificationResult::combine(presentations.clone(), &[vec!["0"], vec!["1"]]).unwrap();
+        assert!(result.verdict.is_verified());
+        assert_eq!(result.presentations.len(), 2);
+
+        // Both are required: the failed presentation fails the response.
+        let result = VerificationResult::combine(presentations, &[vec!["0", "1"]]).unwrap();
+        assert_eq!(result.verdict.code(), "issuer_untrusted");
+    }
+}
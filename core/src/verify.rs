@@ -0,0 +1,328 @@
+use anyhow::{Context, Result, bail};
+use ciborium::Value as Cbor;
+use coset::Label;
+use isomdl::{
+    cbor,
+    definitions::{
+        device_response::Document,
+        helpers::Tag24,
+        mso::Mso,
+        validity_info::ValidityInfo,
+        x509::{trust_anchor::TrustAnchorRegistry, x5chain::X5CHAIN_COSE_HEADER_LABEL},
+    },
+    presentation::authentication::ResponseAuthenticationOutcome,
+};
+use pkcs8::der::Decode;
+use serde_json::json;
+use x509_cert::Certificate;
+
+use crate::{
+    aamva::AamvaExtensions,
+    clock::Clock,
+    crl::{CRL_UNAVAILABLE, CrlChecker},
+    disclosure::RequestedElements,
+    eu_pid::EuPid,
+    issuer::{DOC_TYPE_MISMATCH, IssuerFacts},
+    mdl::MobileDrivingLicence,
+    policy::VerificationPolicy,
+    rules::RulePolicy,
+    schema::{EU_PID_NAMESPACE, IssuerElements, issuer_elements},
+    sd_jwt::SdJwtVc,
+    status_list::{
+        STATUS_LIST_UNAVAILABLE, StatusListChecker, StatusListReference, mso_status_list,
+    },
+    validity::check_certificate_validity,
+    verdict::{
+        CERTIFICATE_VALIDITY_ERRORS, PARSING_ERRORS, RevocationReport, VerdictReason,
+        VerificationResult,
+    },
+};
+
+/// The namespace of the ISO/IEC 18013-5 mDL data elements.
+pub const MDL_NAMESPACE: &str = "org.iso.18013.5.1";
+
+/// Code of the [VerdictReason] reporting a Mobile Security Object that could
+/// not be decoded, so that its validity and doctype cannot be checked.
+pub const INVALID_MSO: &str = "invalid_mso";
+
+/// What a response is verified against, besides the initiated session state.
+#[derive(Clone, Copy)]
+pub struct VerificationContext<'a> {
+    pub trust_anchor_registry: &'a TrustAnchorRegistry,
+    pub policy: &'a VerificationPolicy,
+    pub clock: &'a dyn Clock,
+    /// Checks issuer certificates for revocation when set.
+    pub crl_checker: Option<&'a CrlChecker>,
+    /// Checks credentials referencing a status list for revocation when set.
+    pub status_list_checker: Option<&'a StatusListChecker>,
+}
+
+impl<'a> VerificationContext<'a> {
+    pub fn new(
+        trust_anchor_registry: &'a TrustAnchorRegistry,
+        policy: &'a VerificationPolicy,
+        clock: &'a dyn Clock,
+    ) -> Self {
+        Self {
+            trust_anchor_registry,
+            policy,
+            clock,
+            crl_checker: None,
+            status_list_checker: None,
+        }
+    }
+
+    pub fn with_crl_checker(mut self, crl_checker: Option<&'a CrlChecker>) -> Self {
+        self.crl_checker = crl_checker;
+        self
+    }
+
+    pub fn with_status_list_checker(
+        mut self,
+        status_list_checker: Option<&'a StatusListChecker>,
+    ) -> Self {
+        self.status_list_checker = status_list_checker;
+        self
+    }
+
+    /// Applies the schema, revocation, issuer, validity and disclosure checks, then
+    /// the `rules` attached to the request, to an authenticated response.
+    ///
+    /// The certificates of the issuer are checked to be valid as of the time
+    /// of the [Clock], reported under [CERTIFICATE_VALIDITY_ERRORS].
+    pub(crate) async fn check(
+        &self,
+        outcome: ResponseAuthenticationOutcome,
+        issuer_signed: Option<IssuerSigned>,
+        doc_type: Option<&str>,
+        requested: &RequestedElements,
+        rules: &RulePolicy,
+    ) -> VerificationResult {
+        let now = self.clock.now();
+        let mut outcome = outcome;
+        if let Some(issuer_signed) = &issuer_signed
+            && !outcome.errors.contains_key(PARSING_ERRORS)
+        {
+            let errors: Vec<String> = issuer_signed
+                .certification_path(self.trust_anchor_registry)
+                .iter()
+                .filter_map(|certificate| check_certificate_validity(certificate, now).err())
+                .map(|e| e.to_string())
+                .collect();
+            if !errors.is_empty() {
+                outcome
+                    .errors
+                    .insert(CERTIFICATE_VALIDITY_ERRORS.to_string(), json!(errors));
+            }
+        }
+        let mut result = VerificationResult::from(outcome);
+        if let Some(issuer_signed) = issuer_signed {
+            result.check_schema(&issuer_signed.elements, self.policy);
+            result.mdl = MobileDrivingLicence::from_response(&result.outcome.response);
+            result.aamva = AamvaExtensions::from_response(&result.outcome.response);
+            result.eu_pid = EuPid::from_response(&result.outcome.response);
+            let path = issuer_signed.certification_path(self.trust_anchor_registry);
+            if result.verdict.is_verified() {
+                let mut report = RevocationReport::default();
+                if let Some(crl_checker) = self.crl_checker {
+                    match &issuer_signed.x5chain_error {
+                        // Without the issuer certificates, revocation is unknown.
+                        Some(error) => report.unknown.push(VerdictReason {
+                            code: CRL_UNAVAILABLE.to_string(),
+                            detail: json!({ "detail": error }),
+                        }),
+                        None => report.extend(crl_checker.check(&path, now).await),
+                    }
+                }
+                if let Some(status_list_checker) = self.status_list_checker {
+                    match &issuer_signed.status_list {
+                        Ok(Some(status_list)) => {
+                            report.extend(status_list_checker.check(status_list, &path, now).await)
+                        }
+                        Ok(None) => {}
+                        // A status that cannot be decoded cannot be checked.
+                        Err(error) => report.unknown.push(VerdictReason {
+                            code: STATUS_LIST_UNAVAILABLE.to_string(),
+                            detail: json!({ "detail": error }),
+                        }),
+                    }
+                }
+                result.check_revocation(report, self.policy);
+            }
+            let anchor = path.last().filter(|certificate| {
+                self.trust_anchor_registry
+                    .anchors
+                    .iter()
+                    .any(|anchor| anchor.certificate == **certificate)
+            });
+            let issuing_country = [MDL_NAMESPACE, EU_PID_NAMESPACE]
+                .into_iter()
+                .find_map(|namespace| {
+                    result
+                        .outcome
+                        .response
+                        .get(namespace)?
+                        .get("issuing_country")?
+                        .as_str()
+                })
+                .map(str::to_string);
+            let reasons = self.policy.issuer.check(&IssuerFacts {
+                document_signer: path.first(),
+                anchor,
+                doc_type: issuer_signed.doc_type.as_deref(),
+                issuing_country: issuing_country.as_deref(),
+            });
+            for reason in reasons {
+                result.reject(reason);
+            }
+            // The doctype of the document must be the one its issuer signed.
+            if issuer_signed.doc_type.is_none()
+                || issuer_signed.document_doc_type != issuer_signed.doc_type
+            {
+                result.reject(VerdictReason {
+                    code: DOC_TYPE_MISMATCH.to_string(),
+                    detail: json!({
+                        "docType": issuer_signed.document_doc_type,
+                        "msoDocType": issuer_signed.doc_type,
+                    }),
+                });
+            }
+            if let (Some(expected), Some(returned)) = (doc_type, &issuer_signed.doc_type)
+                && expected != returned
+            {
+                result.reject(VerdictReason {
+                    code: DOC_TYPE_MISMATCH.to_string(),
+                    detail: json!({ "expected": expected, "docType": returned }),
+                });
+            }
+            if let Some(error) = &issuer_signed.mso_error {
+                result.reject(VerdictReason {
+                    code: INVALID_MSO.to_string(),
+                    detail: json!(error),
+                });
+            }
+            if let Some(validity_info) = &issuer_signed.validity_info {
+                result.check_validity(validity_info, &self.policy.validity, now);
+            }
+        }
+        result.check_disclosure(requested, self.policy);
+        result.check_rules(rules, now);
+        result
+    }
+}
+
+/// The issuer-signed data of a presented document that policy checks apply to.
+pub(crate) struct IssuerSigned {
+    /// The doctype signed in the Mobile Security Object.
+    doc_type: Option<String>,
+    /// The doctype the document declares.
+    document_doc_type: Option<String>,
+    elements: IssuerElements,
+    validity_info: Option<ValidityInfo>,
+    /// Why the Mobile Security Object could not be decoded.
+    mso_error: Option<String>,
+    /// The status list reference, or why the status could not be decoded.
+    status_list: Result<Option<StatusListReference>, String>,
+    /// The `x5chain` of the issuer signature, starting with the document signer.
+    x5chain: Vec<Certificate>,
+    /// Why the `x5chain` could not be decoded.
+    x5chain_error: Option<String>,
+}
+
+impl IssuerSigned {
+    pub(crate) fn new(document: &Document) -> Self {
+        let (mso, mso_error) = match issuer_mso(document) {
+            Ok(mso) => (Some(mso), None),
+            Err(e) => (None, Some(format!("{e:#}"))),
+        };
+        let (x5chain, x5chain_error) = match issuer_x5chain(document) {
+            Ok(x5chain) => (x5chain, None),
+            Err(e) => (
+                Vec::new(),
+                Some(format!("Could not decode issuer x5chain: {e:#}")),
+            ),
+        };
+        Self {
+            doc_type: mso.as_ref().map(|mso| mso.doc_type.clone()),
+            document_doc_type: Some(document.doc_type.clone()).filter(|d| !d.is_empty()),
+            elements: issuer_elements(document),
+            validity_info: mso.map(|mso| mso.validity_info),
+            mso_error,
+            status_list: mso_status_list(document)
+                .map_err(|e| format!("Could not decode MSO status: {e:#}")),
+            x5chain,
+            x5chain_error,
+        }
+    }
+
+    /// The issuer-signed data of an SD-JWT VC, whose `vct` stands in for the doctype.
+    pub(crate) fn from_sd_jwt(sd_jwt: SdJwtVc) -> Self {
+        Self {
+            doc_type: Some(sd_jwt.vct.clone()),
+            document_doc_type: Some(sd_jwt.vct),
+            elements: IssuerElements::new(),
+            validity_info: sd_jwt.validity_info,
+            mso_error: None,
+            status_list: sd_jwt.status_list,
+            x5chain: sd_jwt.x5c,
+            x5chain_error: None,
+        }
+    }
+
+    /// The `x5chain`, completed with the trust anchor it chains to if it does
+    /// not end with a self-signed certificate.
+    fn certification_path(&self, trust_anchor_registry: &TrustAnchorRegistry) -> Vec<Certificate> {
+        let mut path = self.x5chain.clone();
+        if let Some(last) = path.last()
+            && last.tbs_certificate.issuer != last.tbs_certificate.subject
+            && let Some(anchor) = trust_anchor_registry.anchors.iter().find(|anchor| {
+                anchor.certificate.tbs_certificate.subject == last.tbs_certificate.issuer
+            })
+        {
+            path.push(anchor.certificate.clone());
+        }
+        path
+    }
+}
+
+/// Decodes the Mobile Security Object signed by the issuer of `document`.
+pub(crate) fn issuer_mso(document: &Document) -> Result<Mso> {
+    let payload = document
+        .issuer_signed
+        .issuer_auth
+        .payload
+        .as_ref()
+        .context("issuer auth has no payload")?;
+    let mso: Tag24<Mso> = cbor::from_slice(payload).context("Could not deserialize MSO")?;
+    Ok(mso.into_inner())
+}
+
+/// Decodes the `x5chain` header of the issuer signature of `document`.
+pub(crate) fn issuer_x5chain(document: &Document) -> Result<Vec<Certificate>> {
+    let x5chain = document
+        .issuer_signed
+        .issuer_auth
+        .unprotected
+        .rest
+        .iter()
+        .find(|(label, _)| *label == Label::Int(X5CHAIN_COSE_HEADER_LABEL))
+        .map(|(_, value)| value)
+        .context("issuer auth has no x5chain")?;
+    let certificates = match x5chain {
+        Cbor::Bytes(certificate) => vec![certificate],
+        Cbor::Array(certificates) => certificates
+            .iter()
+            .map(|certificate| match certificate {
+                Cbor::Bytes(certificate) => Ok(certificate),
+                _ => bail!("x5chain entries must be byte strings"),
+            })
+            .collect::<Result<_>>()?,
+        _ => bail!("x5chain must be a byte string or an array"),
+    };
+    certificates
+        .into_iter()
+        .map(|certificate| {
+            Certificate::from_der(certificate).context("Could not decode certificate")
+        })
+        .collect()
+}
@@ -0,0 +1,446 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use base64::prelude::*;
+use hmac::{Hmac, Mac};
+use http::{Request, StatusCode, header::CONTENT_TYPE};
+use serde::Serialize;
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use time::Duration;
+use url::Url;
+
+use crate::clock::Clock;
+use crate::events::{SessionEvent, SessionEventHook, SessionEventKind};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Header carrying the webhook signature, formatted as `t=<timestamp>,v1=<signature>`.
+///
+/// The signature is the base64url encoded HMAC-SHA256 of `<timestamp>.<body>`.
+pub const SIGNATURE_HEADER: &str = "dc-api-signature";
+
+/// Sends webhook requests, allowing the emitter to be used with any HTTP
+/// client (e.g. `fetch` from JavaScript, or a local stand-in server in tests).
+#[cfg_attr(target_arch="wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait WebhookTransport {
+    async fn send(&self, request: Request<Vec<u8>>) -> Result<StatusCode>;
+}
+
+/// A shared [WebhookTransport], which must be thread-safe outside of WebAssembly.
+#[cfg(not(target_arch = "wasm32"))]
+pub type SharedWebhookTransport = Arc<dyn WebhookTransport + Send + Sync>;
+#[cfg(target_arch = "wasm32")]
+pub type SharedWebhookTransport = Arc<dyn WebhookTransport>;
+
+/// The webhook body sent by default: the session, its new state and, for a
+/// completed session, the verdict, without any disclosed element.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WebhookPayload<'a> {
+    session_id: &'a str,
+    timestamp: i64,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    verdict: Option<&'static str>,
+}
+
+impl<'a> From<&'a SessionEvent> for WebhookPayload<'a> {
+    fn from(event: &'a SessionEvent) -> Self {
+        Self {
+            session_id: &event.session_id,
+            timestamp: event.timestamp,
+            kind: event.kind.name(),
+            verdict: match &event.kind {
+                SessionEventKind::Completed { result } => Some(result.verdict.code()),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// A [SessionEventHook] posting signed JSON events to a webhook endpoint.
+#[derive(Clone)]
+pub struct WebhookEmitter {
+    url: Url,
+    secret: Vec<u8>,
+    transport: SharedWebhookTransport,
+    include_outcome: bool,
+}
+
+impl WebhookEmitter {
+    pub fn new(url: Url, secret: Vec<u8>, transport: SharedWebhookTransport) -> Self {
+        Self {
+            url,
+            secret,
+            transport,
+            include_outcome: false,
+        }
+    }
+
+    /// Posts the full [SessionEvent], including the verification outcome with
+    /// the disclosed elements and failure reasons, instead of only the session
+    /// id, state and verdict.
+    pub fn with_outcome(mut self) -> Self {
+        self.include_outcome = true;
+        self
+    }
+
+    pub fn build_request(&self, event: &SessionEvent) -> Result<Request<Vec<u8>>> {
+        let body = if self.include_outcome {
+            serde_json::to_vec(event)
+        } else {
+            serde_json::to_vec(&WebhookPayload::from(event))
+        }
+        .context("Could not serialize session event")?;
+        let signature = sign(&self.secret, event.timestamp, &body);
+        Request::post(self.url.as_str())
+            .header(CONTENT_TYPE, "application/json")
+            .header(
+                SIGNATURE_HEADER,
+                format!("t={},v1={signature}", event.timestamp),
+            )
+            .body(body)
+            .context("Could not build webhook request")
+    }
+}
+
+#[cfg_attr(target_arch="wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl SessionEventHook for WebhookEmitter {
+    async fn on_event(&self, event: &SessionEvent) -> Result<()> {
+        let request = self.build_request(event)?;
+        let status = self.transport.send(request).await?;
+        if !status.is_success() {
+            bail!("webhook endpoint responded with status {status}");
+        }
+        Ok(())
+    }
+}
+
+fn sign(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC can take a key of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    BASE64_URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+}
+
+/// Verifies the [SIGNATURE_HEADER] of a received webhook, rejecting
+/// signatures more than `tolerance` away from the time of `clock`.
+pub fn verify_signature(
+    secret: &[u8],
+    header: &str,
+    body: &[u8],
+    tolerance: Duration,
+    clock: &dyn Clock,
+) -> Result<()> {
+    let mut timestamp = None;
+    let mut signature = None;
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", t)) => timestamp = Some(t.parse::<i64>().context("Invalid timestamp")?),
+            Some(("v1", v)) => signature = Some(v),
+            _ => {}
+        }
+    }
+    let timestamp = timestamp.ok_or(anyhow!("missing signature timestamp"))?;
+    let signature = signature.ok_or(anyhow!("missing signature"))?;
+
+    let age = clock.now().unix_timestamp().saturating_sub(timestamp);
+    if age.unsigned_abs() > tolerance.whole_seconds().unsigned_abs() {
+        bail!("webhook signature timestamp is outside of the tolerance");
+    }
+
+    let expected = sign(secret, timestamp, body);
+    if !bool::from(expected.as_bytes().ct_eq(signature.as_bytes())) {
+        bail!("webhook signature mismatch");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        io::{BufRead, BufReader, Read, Write},
+        net::{TcpListener, TcpStream},
+        sync::Mutex,
+        thread,
+    };
+
+    use isomdl::presentation::authentication::{
+        AuthenticationStatus, ResponseAuthenticationOutcome,
+    };
+    use serde_json::{Value, json};
+    use time::OffsetDateTime;
+
+    use super::*;
+    use crate::{
+        clock::{FixedClock, SystemClock},
+        events::SessionEventKind,
+        verdict::VerificationResult,
+    };
+
+    const SECRET: &[u8] = b"webhook secret";
+
+    struct ReceivedRequest {
+        path: String,
+        headers: BTreeMap<String, String>,
+        body: Vec<u8>,
+    }
+
+    /// A local HTTP endpoint answering every request with `status`.
+    struct Endpoint {
+        url: Url,
+        received: Arc<Mutex<Vec<ReceivedRequest>>>,
+    }
+
+    impl Endpoint {
+        fn start(status: u16) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/events", listener.local_addr().unwrap())
+                .parse()
+                .unwrap();
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let requests = received.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let request = read_request(&mut stream);
+                    requests.lock().unwrap().push(request);
+                    write!(
+                        stream,
+                        "HTTP/1.1 {status} Status\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                    )
+                    .unwrap();
+                }
+            });
+            Self { url, received }
+        }
+
+        fn received(&self) -> Vec<(String, BTreeMap<String, String>, Value)> {
+            self.received
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|request| {
+                    (
+                        request.path.clone(),
+                        request.headers.clone(),
+                        serde_json::from_slice(&request.body).unwrap(),
+                    )
+                })
+                .collect()
+        }
+
+        fn raw_body(&self) -> Vec<u8> {
+            self.received.lock().unwrap()[0].body.clone()
+        }
+    }
+
+    fn read_request(stream: &mut TcpStream) -> ReceivedRequest {
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let path = line.split_whitespace().nth(1).unwrap().to_string();
+        let mut headers = BTreeMap::new();
+        loop {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            let Some((name, value)) = line.trim_end().split_once(':') else {
+                break;
+            };
+            headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+        }
+        let length = headers
+            .get("content-length")
+            .map_or(0, |length| length.parse().unwrap());
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        ReceivedRequest {
+            path,
+            headers,
+            body,
+        }
+    }
+
+    /// Sends requests over plain HTTP/1.1.
+    struct HttpTransport;
+
+    #[async_trait]
+    impl WebhookTransport for HttpTransport {
+        async fn send(&self, request: Request<Vec<u8>>) -> Result<StatusCode> {
+            let authority = request.uri().authority().context("no authority")?.as_str();
+            let mut stream = TcpStream::connect(authority)?;
+            write!(
+                stream,
+                "POST {} HTTP/1.1\r\nhost: {authority}\r\n",
+                request.uri().path()
+            )?;
+            for (name, value) in request.headers() {
+                write!(stream, "{name}: {}\r\n", value.to_str()?)?;
+            }
+            write!(
+                stream,
+                "content-length: {}\r\nconnection: close\r\n\r\n",
+                request.body().len()
+            )?;
+            stream.write_all(request.body())?;
+            let mut response = String::new();
+            stream.read_to_string(&mut response)?;
+            let status = response
+                .split_whitespace()
+                .nth(1)
+                .context("empty response")?;
+            Ok(StatusCode::from_u16(status.parse()?)?)
+        }
+    }
+
+    fn completed_event() -> SessionEvent {
+        let mut outcome = ResponseAuthenticationOutcome {
+            issuer_authentication: AuthenticationStatus::Valid,
+            device_authentication: AuthenticationStatus::Valid,
+            ..Default::default()
+        };
+        outcome.response.insert(
+            "org.iso.18013.5.1".to_string(),
+            json!({ "family_name": "Doe", "birth_date": "1990-01-01" }),
+        );
+        SessionEvent::new(
+            "session-1".to_string(),
+            SessionEventKind::Completed {
+                result: VerificationResult::from(outcome),
+            },
+            OffsetDateTime::now_utc(),
+        )
+    }
+
+    fn emitter(endpoint: &Endpoint) -> WebhookEmitter {
+        WebhookEmitter::new(
+            endpoint.url.clone(),
+            SECRET.to_vec(),
+            Arc::new(HttpTransport),
+        )
+    }
+
+    #[tokio::test]
+    async fn posts_signed_event_without_outcome() {
+        let endpoint = Endpoint::start(200);
+        let event = completed_event();
+
+        emitter(&endpoint).on_event(&event).await.unwrap();
+
+        let received = endpoint.received();
+        assert_eq!(received.len(), 1);
+        let (path, headers, body) = &received[0];
+        assert_eq!(path, "/events");
+        assert_eq!(headers["content-type"], "application/json");
+        assert_eq!(
+            body,
+            &json!({
+                "sessionId": "session-1",
+                "timestamp": event.timestamp,
+                "type": "completed",
+                "verdict": "verified",
+            })
+        );
+        verify_signature(
+            SECRET,
+            &headers[SIGNATURE_HEADER],
+            &endpoint.raw_body(),
+            Duration::minutes(1),
+            &SystemClock,
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn posts_outcome_when_enabled() {
+        let endpoint = Endpoint::start(200);
+
+        emitter(&endpoint)
+            .with_outcome()
+            .on_event(&completed_event())
+            .await
+            .unwrap();
+
+        let (_, _, body) = &endpoint.received()[0];
+        assert_eq!(
+            body["result"]["outcome"]["response"]["org.iso.18013.5.1"]["family_name"],
+            "Doe"
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_unsuccessful_status() {
+        let endpoint = Endpoint::start(500);
+
+        let error = emitter(&endpoint)
+            .on_event(&completed_event())
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().contains("500"));
+        assert_eq!(endpoint.received().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_tampered_body() {
+        let endpoint = Endpoint::start(200);
+
+        emitter(&endpoint)
+            .on_event(&completed_event())
+            .await
+            .unwrap();
+
+        let (_, headers, _) = &endpoint.received()[0];
+        let mut body = endpoint.raw_body();
+        body.extend_from_slice(b" ");
+        assert!(
+            verify_signature(
+                SECRET,
+                &headers[SIGNATURE_HEADER],
+                &body,
+                Duration::minutes(1),
+                &SystemClock,
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn checks_signature_age_against_clock() {
+        let event = completed_event();
+        let request = emitter(&Endpoint::start(200))
+            .build_request(&event)
+            .unwrap();
+        let header = request.headers()[SIGNATURE_HEADER].to_str().unwrap();
+        let signed_at = OffsetDateTime::from_unix_timestamp(event.timestamp).unwrap();
+        let verify = |now: OffsetDateTime| {
+            verify_signature(
+                SECRET,
+                header,
+                request.body(),
+                Duration::minutes(1),
+                &FixedClock(now),
+            )
+        };
+
+        assert!(verify(signed_at + Duration::seconds(30)).is_ok());
+        assert!(verify(signed_at + Duration::minutes(2)).is_err());
+        assert!(verify(signed_at - Duration::minutes(2)).is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_range_timestamps() {
+        let header = format!("t={},v1=signature", i64::MIN);
+        assert!(
+            verify_signature(SECRET, &header, b"{}", Duration::minutes(1), &SystemClock).is_err()
+        );
+    }
+}
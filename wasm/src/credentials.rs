@@ -0,0 +1,175 @@
+//! TypeScript declarations of the typed credentials of a verification result.
+
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_MOBILE_DRIVING_LICENCE: &'static str = r#"
+/**
+ * The `mdl` of a verification result, from the `org.iso.18013.5.1` namespace.
+ * Elements that were not returned, or that do not match their schema, are
+ * `undefined`.
+ */
+export interface MobileDrivingLicence {
+    family_name?: string;
+    given_name?: string;
+    /** `YYYY-MM-DD`, as are the other dates unless noted otherwise. */
+    birth_date?: string;
+    issue_date?: string;
+    expiry_date?: string;
+    issuing_country?: string;
+    issuing_authority?: string;
+    issuing_jurisdiction?: string;
+    document_number?: string;
+    administrative_number?: string;
+    un_distinguishing_sign?: string;
+    /** A `data:` URL of the JPEG or JPEG 2000 portrait. */
+    portrait?: string;
+    /** RFC 3339 date-time. */
+    portrait_capture_date?: string;
+    /** A `data:` URL of the signature or usual mark image. */
+    signature_usual_mark?: string;
+    driving_privileges: DrivingPrivilege[];
+    /** ISO/IEC 5218: 0 not known, 1 male, 2 female, 9 not applicable. */
+    sex?: number;
+    /** In centimetres. */
+    height?: number;
+    /** In kilograms. */
+    weight?: number;
+    eye_colour?: string;
+    hair_colour?: string;
+    birth_place?: string;
+    nationality?: string;
+    resident_address?: string;
+    resident_city?: string;
+    resident_state?: string;
+    resident_postal_code?: string;
+    resident_country?: string;
+    family_name_national_character?: string;
+    given_name_national_character?: string;
+    age_in_years?: number;
+    age_birth_year?: number;
+    /** The returned `age_over_NN` elements, by `NN`. */
+    age_over: Map<number, boolean>;
+}
+
+/** A vehicle category the holder is allowed to drive, ISO/IEC 18013-5 7.2.4. */
+export interface DrivingPrivilege {
+    vehicle_category_code: string;
+    issue_date?: string;
+    expiry_date?: string;
+    /** Restrictions and conditions. */
+    codes: { code: string; sign?: string; value?: string }[];
+}
+"#;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_AAMVA_EXTENSIONS: &'static str = r#"
+/**
+ * The `aamva` of a verification result, from the `org.iso.18013.5.1.aamva`
+ * namespace. Elements that were not returned, or whose value does not match
+ * the AAMVA encoding, are `undefined`.
+ */
+export interface AamvaExtensions {
+    /** `DHS_compliance`, REAL ID compliance. */
+    dhs_compliance?: "fully_compliant" | "non_compliant";
+    dhs_compliance_text?: string;
+    dhs_temporary_lawful_status?: boolean;
+    /** ISO/IEC 5218: 0 not known, 1 male, 2 female, 9 not applicable. */
+    sex?: number;
+    /** e.g. `JR`, `SR` or `III`. */
+    name_suffix?: string;
+    family_name_truncation?: AamvaTruncation;
+    given_name_truncation?: AamvaTruncation;
+    aka_family_name?: string;
+    aka_given_name?: string;
+    aka_suffix?: string;
+    organ_donor?: boolean;
+    veteran?: boolean;
+    cdl_indicator?: boolean;
+    edl_credential?: "enhanced_driver_licence" | "enhanced_identification_card";
+    /** AAMVA weight range code, 0 (up to 31 kg) to 9 (over 146 kg). */
+    weight_range?: number;
+    race_ethnicity?:
+        | "alaskan_or_american_indian"
+        | "asian_or_pacific_islander"
+        | "black"
+        | "hispanic_origin"
+        | "non_hispanic"
+        | "unknown"
+        | "white";
+    /** FIPS county code. */
+    resident_county?: string;
+    /** `YYYY-MM-DD`. */
+    hazmat_endorsement_expiration_date?: string;
+    domestic_driving_privileges: DomesticDrivingPrivilege[];
+    aamva_version?: number;
+}
+
+/** Whether a name was truncated. */
+export type AamvaTruncation = "truncated" | "not_truncated" | "unknown";
+
+export interface DomesticDrivingPrivilege {
+    domestic_vehicle_class?: {
+        domestic_vehicle_class_code: string;
+        domestic_vehicle_class_description?: string;
+        issue_date?: string;
+        expiry_date?: string;
+    };
+    domestic_vehicle_restrictions: {
+        domestic_vehicle_restriction_code?: string;
+        domestic_vehicle_restriction_description?: string;
+    }[];
+    domestic_vehicle_endorsements: {
+        domestic_vehicle_endorsement_code?: string;
+        domestic_vehicle_endorsement_description?: string;
+    }[];
+}
+"#;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_EU_PID: &'static str = r#"
+/**
+ * The `eu_pid` of a verification result, from the `eu.europa.ec.eudi.pid.1`
+ * namespace. Elements that were not returned, or that do not match their
+ * schema, are `undefined`.
+ */
+export interface EuPid {
+    family_name?: string;
+    given_name?: string;
+    /** `YYYY-MM-DD`, as are the other dates. */
+    birth_date?: string;
+    family_name_birth?: string;
+    given_name_birth?: string;
+    birth_place?: string;
+    birth_country?: string;
+    birth_state?: string;
+    birth_city?: string;
+    /** ISO 3166-1 alpha-2 codes. */
+    nationality: string[];
+    /** ISO/IEC 5218: 0 not known, 1 male, 2 female, 9 not applicable. */
+    sex?: number;
+    resident_address?: string;
+    resident_country?: string;
+    resident_state?: string;
+    resident_city?: string;
+    resident_postal_code?: string;
+    resident_street?: string;
+    resident_house_number?: string;
+    personal_administrative_number?: string;
+    email_address?: string;
+    mobile_phone_number?: string;
+    /** A `data:` URL of the JPEG or JPEG 2000 portrait. */
+    portrait?: string;
+    issuance_date?: string;
+    expiry_date?: string;
+    issuing_authority?: string;
+    issuing_country?: string;
+    issuing_jurisdiction?: string;
+    document_number?: string;
+    administrative_number?: string;
+    age_in_years?: number;
+    age_birth_year?: number;
+    /** The returned `age_over_NN` elements, by `NN`. */
+    age_over: Map<number, boolean>;
+}
+"#;
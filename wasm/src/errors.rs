@@ -0,0 +1,74 @@
+use dc_api_core::error::DcApiError;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_DC_API_ERROR: &'static str = r#"
+/** Thrown by `DcApi` methods, `code` is stable and machine-readable. */
+export interface DcApiError extends Error {
+    name: "DcApiError";
+    code: string;
+    /** The HTTP status code the error maps to. */
+    status: number;
+    details: any;
+}
+
+/** The class of the `DcApiError`s, see `dc_api_error_class`. */
+export interface DcApiErrorConstructor {
+    new (message: string, code: string, status: number, details?: any): DcApiError;
+    readonly prototype: DcApiError;
+}
+"#;
+
+#[wasm_bindgen(inline_js = r#"
+export class DcApiError extends Error {
+    constructor(message, code, status, details) {
+        super(message);
+        this.name = "DcApiError";
+        this.code = code;
+        this.status = status;
+        this.details = details;
+    }
+}
+
+export function dcApiErrorClass() {
+    return DcApiError;
+}
+"#)]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "DcApiErrorConstructor")]
+    pub type DcApiErrorClass;
+
+    #[wasm_bindgen(js_name = DcApiError)]
+    type JsDcApiError;
+
+    #[wasm_bindgen(constructor, js_class = DcApiError)]
+    fn new(message: &str, code: &str, status: u16, details: JsValue) -> JsDcApiError;
+
+    #[wasm_bindgen(js_name = dcApiErrorClass)]
+    fn error_class() -> DcApiErrorClass;
+}
+
+/// Returns the `DcApiError` class, e.g. for `error instanceof DcApiError`.
+#[wasm_bindgen]
+pub fn dc_api_error_class() -> DcApiErrorClass {
+    error_class()
+}
+
+/// Converts `error` to a `DcApiError`, a JS `Error` with its `code`, `status`
+/// and `details`.
+pub fn js_error(error: impl Into<DcApiError>) -> JsValue {
+    let error = error.into();
+    let details = error
+        .details()
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .unwrap_or(JsValue::NULL);
+
+    JsDcApiError::new(
+        &error.to_string(),
+        error.code(),
+        error.status().as_u16(),
+        details,
+    )
+    .into()
+}
@@ -0,0 +1,40 @@
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use dc_api_core::fetch::Fetcher;
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "Fetcher")]
+    pub type JsFetcher;
+
+    #[wasm_bindgen(method, catch)]
+    async fn fetch(this: &JsFetcher, url: String) -> Result<JsValue, JsValue>;
+}
+
+pub struct JsFetcherDriver {
+    fetcher: JsFetcher,
+}
+
+impl JsFetcherDriver {
+    pub fn new(fetcher: JsFetcher) -> Self {
+        Self { fetcher }
+    }
+}
+
+#[cfg_attr(target_arch="wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Fetcher for JsFetcherDriver {
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+        let body = self
+            .fetcher
+            .fetch(url.to_string())
+            .await
+            .map_err(|e| anyhow!("JavaScript error in fetch: {:?}", e))?;
+
+        body.dyn_into::<Uint8Array>()
+            .map(|body| body.to_vec())
+            .map_err(|_| anyhow!("Fetcher.fetch must return a Uint8Array"))
+    }
+}
@@ -1,38 +1,79 @@
 #![cfg(target_arch = "wasm32")]
-#![allow(clippy::arc_with_non_send_sync)]
 
+mod credentials;
+mod errors;
+mod fetch;
 pub mod sessions;
 
 use std::sync::Arc;
 
 use dc_api_core::client::{OID4VPClient, OID4VPVerifier};
+use dc_api_core::clock::{Clock, FixedClock, SystemClock};
 use dc_api_core::config::{Client, Oid4VpConfig};
+use dc_api_core::crl::CrlChecker;
+use dc_api_core::error::DcApiError;
+use dc_api_core::events::EventSpawner;
 use dc_api_core::isomdl::definitions::x509::trust_anchor::{
     TrustAnchor, TrustAnchorRegistry, TrustPurpose,
 };
-use dc_api_core::session::{SessionState, SessionStorage};
+use dc_api_core::openid4vp::verifier::session::SessionStore;
+use dc_api_core::policy::VerificationPolicy;
+use dc_api_core::presets::RequestPreset;
+use dc_api_core::rules::RulePolicy;
+use dc_api_core::sealed::{SealedOid4VpSessionStore, SealedSessions, SessionSealer};
+use dc_api_core::session::{
+    Session, SessionBinding, SessionState, SessionStorage, remove_oid4vp_session,
+};
+use dc_api_core::status_list::StatusListChecker;
+use dc_api_core::time::{Duration, OffsetDateTime};
 use dc_api_core::types::{
     DCAPINamespaceRequest, DCAPIRequest, DCAPIRequestOpenId4VP, DCAPIRequests, DCAPIResponse,
 };
 use dc_api_core::url::Url;
+use dc_api_core::validity::ValidityPolicy;
+use dc_api_core::verdict::VerificationResult;
+use dc_api_core::verify::VerificationContext;
+use dc_api_core::webhook::WebhookEmitter;
 use dc_api_core::x509_cert::certificate::CertificateInner;
 use dc_api_core::{annex_c as ac, annex_d as ad};
+use serde::Serialize;
+use uuid::Uuid;
 use wasm_bindgen::prelude::*;
 
 pub use sessions::JsOid4VpSessionStore;
 
-use crate::sessions::{JsDcApiSessionDriver, JsDcApiSessionStore};
+use crate::errors::js_error;
+use crate::fetch::{JsFetcher, JsFetcherDriver};
+use crate::sessions::{
+    JsDcApiSessionDriver, JsDcApiSessionStore, JsNonceCache, JsNonceCacheDriver,
+    JsSessionEventHook, JsSessionEventHookDriver, JsWebhookTransport, JsWebhookTransportDriver,
+    LocalEventSpawner,
+};
 
 #[wasm_bindgen]
 pub struct DcApi {
     verifier: OID4VPVerifier,
+    sealed_verifier: OID4VPVerifier,
     config: Oid4VpConfig,
     dc_api_session: SessionStorage,
+    oid4vp_session_store: JsOid4VpSessionStore,
+    sealed_sessions: Option<SealedSessions>,
     trust_anchor_registry: TrustAnchorRegistry,
+    policy: VerificationPolicy,
+    clock: Arc<dyn Clock>,
+    crl_checker: Option<CrlChecker>,
+    status_list_checker: Option<StatusListChecker>,
+}
+
+#[derive(Serialize)]
+struct SealedInitiateResponse {
+    requests: Vec<DCAPIRequest>,
+    token: String,
 }
 
 #[wasm_bindgen]
 impl DcApi {
+    #[allow(clippy::arc_with_non_send_sync)]
     #[wasm_bindgen]
     pub async fn new(
         key: String,
@@ -42,29 +83,44 @@ impl DcApi {
         cert_chain_pem: Vec<u8>,
         oid4vp_session_store: JsOid4VpSessionStore,
         js_dc_api_session_store: JsDcApiSessionStore,
+        client_secret_pepper: Option<Vec<u8>>,
     ) -> Result<Self, JsValue> {
         let x5c = CertificateInner::load_pem_chain(&cert_chain_pem)
-            .map_err(|e| JsValue::from(e.to_string()))?;
+            .map_err(|e| js_error(DcApiError::InvalidRequest(e.to_string())))?;
 
         let base_url = base_url
             .parse::<Url>()
-            .map_err(|e| JsValue::from(e.to_string()))?;
+            .map_err(|e| js_error(DcApiError::InvalidRequest(e.to_string())))?;
         let config = Oid4VpConfig {
             base_url,
             submission_endpoint,
             reference_endpoint,
+            allowed_origins: Vec::new(),
             client: Client {
                 key,
                 x5c: x5c.clone(),
             },
         };
-        let oid4vp_client = OID4VPClient::new(&config).map_err(|e| JsValue::from(e.to_string()))?;
-        let verifier = OID4VPVerifier::new(&config, oid4vp_client, Arc::new(oid4vp_session_store))
-            .await
-            .map_err(|e| JsValue::from(e.to_string()))?;
+        let oid4vp_client = OID4VPClient::new(&config).map_err(js_error)?;
+        let verifier = OID4VPVerifier::new(
+            &config,
+            oid4vp_client.clone(),
+            Arc::new(oid4vp_session_store.clone()),
+        )
+        .await
+        .map_err(js_error)?;
+        // Sealed requests do not write to the OID4VP session store either.
+        let sealed_verifier =
+            OID4VPVerifier::new(&config, oid4vp_client, Arc::new(SealedOid4VpSessionStore))
+                .await
+                .map_err(js_error)?;
 
-        let dc_api_session =
+        let mut dc_api_session =
             SessionStorage::new(Arc::new(JsDcApiSessionDriver::new(js_dc_api_session_store)));
+        if let Some(pepper) = client_secret_pepper {
+            dc_api_session = dc_api_session.with_pepper(pepper);
+        }
+        dc_api_session.set_event_spawner(Some(Arc::new(LocalEventSpawner)));
 
         // Construct trust anchor registry
         let trust_anchor_registry = TrustAnchorRegistry {
@@ -79,19 +135,241 @@ impl DcApi {
 
         Ok(Self {
             verifier,
+            sealed_verifier,
             config,
             dc_api_session,
+            oid4vp_session_store,
+            sealed_sessions: None,
             trust_anchor_registry,
+            policy: VerificationPolicy::default(),
+            clock: Arc::new(SystemClock),
+            crl_checker: None,
+            status_list_checker: None,
         })
     }
 
+    /// Enables the stateless flow, where the initiated session state is sealed
+    /// into a token held by the caller instead of the DC API session store.
+    /// Sealed requests are not recorded in the OID4VP session store either.
+    ///
+    /// `key` must be 32 bytes long and shared by every instance that may
+    /// receive the submission.
+    #[allow(clippy::arc_with_non_send_sync)]
+    #[wasm_bindgen]
+    pub fn enable_sealed_sessions(
+        &mut self,
+        key: Vec<u8>,
+        nonce_cache: JsNonceCache,
+    ) -> Result<(), JsValue> {
+        let sealer = SessionSealer::new(&key).map_err(js_error)?;
+        let mut sealed_sessions =
+            SealedSessions::new(sealer, Arc::new(JsNonceCacheDriver::new(nonce_cache)));
+        sealed_sessions.set_clock(self.clock.clone());
+        self.sealed_sessions = Some(sealed_sessions);
+        Ok(())
+    }
+
+    /// Evaluates every time-dependent check (session expiry, sealed token expiry,
+    /// credential and certificate validity) as of `unix_timestamp` instead of
+    /// the current time, e.g. to re-verify a captured response. `null` restores
+    /// the system clock.
+    ///
+    /// The underlying mdoc library also checks mdoc certificates against the
+    /// system clock, which a fixed time cannot override.
+    #[wasm_bindgen]
+    pub fn set_fixed_time(&mut self, unix_timestamp: Option<f64>) -> Result<(), JsValue> {
+        self.clock = match unix_timestamp {
+            Some(unix_timestamp) => {
+                let now = OffsetDateTime::from_unix_timestamp(unix_timestamp as i64)
+                    .map_err(|e| js_error(DcApiError::InvalidRequest(e.to_string())))?;
+                Arc::new(FixedClock(now))
+            }
+            None => Arc::new(SystemClock),
+        };
+        self.dc_api_session.set_clock(self.clock.clone());
+        if let Some(sealed_sessions) = &mut self.sealed_sessions {
+            sealed_sessions.set_clock(self.clock.clone());
+        }
+        Ok(())
+    }
+
+    /// Restricts the origins requests can be initiated for, either exact
+    /// (`https://rp.example.com`) or matching any subdomain (`https://*.example.com`).
+    ///
+    /// By default, only the origin of `base_url` is allowed.
+    #[wasm_bindgen]
+    pub fn set_allowed_origins(&mut self, allowed_origins: Vec<String>) {
+        self.config.allowed_origins = allowed_origins;
+    }
+
+    /// Rejects responses disclosing data elements that were not requested
+    /// with a `policy_violation` verdict. Over-disclosed and withheld elements
+    /// are reported in the result either way.
+    #[wasm_bindgen]
+    pub fn set_strict_disclosure(&mut self, strict: bool) {
+        self.policy.strict_disclosure = strict;
+    }
+
+    /// Rejects responses with mDL, AAMVA or EU PID data elements that do not
+    /// match their schema with a `policy_violation` verdict. Invalid elements
+    /// are reported in the result either way.
+    #[wasm_bindgen]
+    pub fn set_strict_schema(&mut self, strict: bool) {
+        self.policy.strict_schema = strict;
+    }
+
+    /// Configures the checks on the credential `validityInfo`: `clockSkewSeconds`
+    /// (defaults to 300), `maxCredentialAgeSeconds` and `enforceExpectedUpdate`.
+    #[wasm_bindgen]
+    pub fn set_validity_policy(&mut self, policy: JsValue) -> Result<(), JsValue> {
+        let policy: ValidityPolicy = serde_wasm_bindgen::from_value(policy)
+            .map_err(|e| js_error(DcApiError::InvalidRequest(e.to_string())))?;
+        policy
+            .validate()
+            .map_err(|e| js_error(DcApiError::InvalidRequest(e.to_string())))?;
+        self.policy.validity = policy;
+        Ok(())
+    }
+
+    /// Restricts the accepted credentials by issuer country, issuer and
+    /// doctype, and the doctypes each trust anchor is authorized to issue.
+    ///
+    /// `policy` is a JSON object in the camelCase form of `IssuerPolicy`.
+    #[wasm_bindgen]
+    pub fn set_issuer_policy(&mut self, policy: JsValue) -> Result<(), JsValue> {
+        self.policy.issuer = serde_wasm_bindgen::from_value(policy)
+            .map_err(|e| js_error(DcApiError::InvalidRequest(e.to_string())))?;
+        Ok(())
+    }
+
+    /// Checks the document signer and IACA certificates against the CRLs
+    /// listed in their CRL distribution points, retrieved with `fetcher`.
+    ///
+    /// CRLs are cached until their `nextUpdate`.
+    #[allow(clippy::arc_with_non_send_sync)]
+    #[wasm_bindgen]
+    pub fn enable_crl_checks(&mut self, fetcher: JsFetcher) {
+        self.crl_checker = Some(CrlChecker::new(Arc::new(JsFetcherDriver::new(fetcher))));
+    }
+
+    /// Checks credentials referencing an IETF Token Status List from their MSO
+    /// against that list, retrieved with `fetcher` in its JWT or CWT form.
+    ///
+    /// Status lists are cached until they expire.
+    #[allow(clippy::arc_with_non_send_sync)]
+    #[wasm_bindgen]
+    pub fn enable_status_list_checks(&mut self, fetcher: JsFetcher) {
+        self.status_list_checker = Some(StatusListChecker::new(Arc::new(JsFetcherDriver::new(
+            fetcher,
+        ))));
+    }
+
+    /// Accepts responses whose revocation status could not be determined,
+    /// e.g. as a CRL could not be fetched. Such responses are rejected by default.
+    #[wasm_bindgen]
+    pub fn set_soft_fail_revocation(&mut self, soft_fail: bool) {
+        self.policy.soft_fail_revocation = soft_fail;
+    }
+
+    /// Sets how many times a request can be initiated within one session,
+    /// e.g. after the user dismissed the wallet prompt. Defaults to 3.
+    #[wasm_bindgen]
+    pub fn set_max_initiation_attempts(&mut self, max_attempts: u32) {
+        self.dc_api_session
+            .set_max_initiation_attempts(max_attempts);
+    }
+
+    /// Sets after how many failed submissions a session is locked. Defaults to 5.
+    #[wasm_bindgen]
+    pub fn set_max_failed_submissions(&mut self, max_failures: u32) {
+        self.dc_api_session.set_max_failed_submissions(max_failures);
+    }
+
+    /// Sessions created from now on expire if they are not completed within `ttl_seconds`.
+    #[wasm_bindgen]
+    pub fn set_session_ttl(&mut self, ttl_seconds: u32) {
+        self.dc_api_session
+            .set_session_ttl(Duration::seconds(ttl_seconds.into()));
+    }
+
+    /// Age after which `sweep_sessions` expires sessions created without a TTL
+    /// that were not completed. Defaults to one day.
+    #[wasm_bindgen]
+    pub fn set_max_session_age(&mut self, max_age_seconds: u32) {
+        self.dc_api_session
+            .set_max_session_age(Duration::seconds(max_age_seconds.into()));
+    }
+
+    /// Registers a callback invoked on every session state transition.
+    #[allow(clippy::arc_with_non_send_sync)]
+    #[wasm_bindgen]
+    pub fn add_session_event_hook(&mut self, hook: JsSessionEventHook) {
+        self.dc_api_session
+            .add_event_hook(Arc::new(JsSessionEventHookDriver::new(hook)));
+    }
+
+    /// Awaits the event hooks and webhooks before returning from the call that
+    /// caused a state transition, e.g. on runtimes that stop pending work once
+    /// a response is sent. By default, events are delivered in the background.
+    #[wasm_bindgen]
+    pub fn set_await_event_hooks(&mut self, await_hooks: bool) {
+        let spawner: Option<Arc<dyn EventSpawner>> = if await_hooks {
+            None
+        } else {
+            Some(Arc::new(LocalEventSpawner))
+        };
+        self.dc_api_session.set_event_spawner(spawner);
+    }
+
+    /// Posts signed session events to `url`, see `verify_webhook_signature`.
+    ///
+    /// Events only carry the session id, state and verdict, unless
+    /// `include_outcome` is set.
+    #[allow(clippy::arc_with_non_send_sync)]
     #[wasm_bindgen]
-    pub async fn create_new_session(&self) -> Result<JsValue, JsValue> {
+    pub fn add_webhook(
+        &mut self,
+        url: String,
+        secret: Vec<u8>,
+        transport: JsWebhookTransport,
+        include_outcome: Option<bool>,
+    ) -> Result<(), JsValue> {
+        let url = url
+            .parse::<Url>()
+            .map_err(|e| js_error(DcApiError::InvalidRequest(e.to_string())))?;
+        let mut emitter = WebhookEmitter::new(
+            url,
+            secret,
+            Arc::new(JsWebhookTransportDriver::new(transport)),
+        );
+        if include_outcome.unwrap_or_default() {
+            emitter = emitter.with_outcome();
+        }
+        self.dc_api_session.add_event_hook(Arc::new(emitter));
+        Ok(())
+    }
+
+    /// Creates a new session.
+    ///
+    /// When `origin` is provided, the session is bound to it and, if provided,
+    /// to `client_binding` (e.g. a hash of a cookie), which must then be
+    /// presented again to [DcApi::initiate_request] and [DcApi::submit_response].
+    #[wasm_bindgen]
+    pub async fn create_new_session(
+        &self,
+        origin: Option<String>,
+        client_binding: Option<String>,
+    ) -> Result<JsValue, JsValue> {
+        let binding = origin.map(|origin| SessionBinding::new(origin, client_binding.as_deref()));
         let session = self
             .dc_api_session
-            .new_session()
+            .new_session(binding)
             .await
-            .map_err(|e| JsValue::from(format!("Failed to save new dc-api session: {e:?}")))?;
+            .map_err(|e| {
+                js_error(DcApiError::Storage(format!(
+                    "Failed to save new session: {e:#}"
+                )))
+            })?;
 
         let value = serde_wasm_bindgen::to_value(&session)?;
 
@@ -105,54 +383,44 @@ impl DcApi {
         session_secret: String,
         request: JsValue,
         user_agent: Option<String>,
+        client_binding: Option<String>,
     ) -> Result<JsValue, JsValue> {
         let mut session = self
             .dc_api_session
             .get_session(session_id.clone(), session_secret.clone())
             .await
-            .map_err(|e| JsValue::from(e.to_string()))?
-            .ok_or(JsValue::from_str("Session not found"))?;
+            .map_err(js_error)?
+            .ok_or_else(|| js_error(DcApiError::SessionNotFound))?;
 
-        if session.state != SessionState::Created {
-            return Err(JsValue::from_str("Invalid session"));
+        let request: DCAPINamespaceRequest = serde_wasm_bindgen::from_value(request)
+            .map_err(|e| js_error(DcApiError::InvalidRequest(e.to_string())))?;
+
+        if let Some(binding) = &session.binding {
+            binding
+                .check(&request.origin, client_binding.as_deref())
+                .map_err(js_error)?;
         }
 
-        let request: DCAPINamespaceRequest = serde_wasm_bindgen::from_value(request)?;
+        session
+            .begin_initiation(self.dc_api_session.max_initiation_attempts())
+            .map_err(js_error)?;
+        // Re-initiating: the previous OID4VP session is replaced.
+        remove_oid4vp_session(&session, &self.oid4vp_session_store).await;
 
-        let (annexc_res, annexc_state) = ac::initiate_inner(&self.config, &request)
-            .await
-            .map_err(|e| JsValue::from(e.to_string()))?;
-        let (annexd_res, annexd_state) =
-            ad::initiate_inner(&self.verifier, &request, session_id.clone())
-                .await
-                .map_err(|e| JsValue::from(e.to_string()))?;
+        let (requests, annex_c, annex_d) = self
+            .build_requests(&self.verifier, &request, session_id.clone(), user_agent)
+            .await?;
 
         // Update the session state with the initiate annex states
-        session.state = SessionState::Initiated {
-            annex_c: annexc_state,
-            annex_d: annexd_state,
-        };
+        session.oid4vp_session_id = Some(annex_d.oid4vp_session_id().to_string());
+        session.state = SessionState::Initiated { annex_c, annex_d };
 
         self.dc_api_session
             .update_session(session_id, session)
             .await
-            .map_err(|e| JsValue::from(e.to_string()))?;
-
-        let mut requests = vec![DCAPIRequest::OpenId4VP {
-            data: DCAPIRequestOpenId4VP {
-                request: annexd_res.request_jwt,
-            },
-        }];
-
-        // NOTE: Chrome crashes on processing the DC API response
-        // when the request contains more than one request.
-        if let Some(user_agent) = user_agent
-            && !user_agent.as_str().contains("Chrome")
-        {
-            requests.push(DCAPIRequest::OrgIsoMDoc { data: annexc_res });
-        }
+            .map_err(js_error)?;
 
-        let value = serde_wasm_bindgen::to_value(&DCAPIRequests { requests })?;
+        let value = serde_wasm_bindgen::to_value(&requests)?;
 
         Ok(value)
     }
@@ -163,55 +431,369 @@ impl DcApi {
         session_id: String,
         session_secret: String,
         response: JsValue,
+        origin: Option<String>,
+        client_binding: Option<String>,
     ) -> Result<JsValue, JsValue> {
         let mut session = self
             .dc_api_session
             .get_session(session_id.clone(), session_secret.clone())
             .await
-            .map_err(|e| JsValue::from(e.to_string()))?
-            .ok_or(JsValue::from_str("Session not found"))?;
+            .map_err(js_error)?
+            .ok_or_else(|| js_error(DcApiError::SessionNotFound))?;
+
+        let (annex_c, annex_d) = self
+            .dc_api_session
+            .begin_submission(
+                session_id.clone(),
+                &session,
+                origin.as_deref(),
+                client_binding.as_deref(),
+            )
+            .await
+            .map_err(js_error)?;
 
-        let response: DCAPIResponse = serde_wasm_bindgen::from_value(response)?;
+        let response: DCAPIResponse = match serde_wasm_bindgen::from_value(response) {
+            Ok(response) => response,
+            Err(e) => {
+                let e = DcApiError::InvalidResponse(e.to_string());
+                return Err(self.record_failed_submission(session_id, session, e).await);
+            }
+        };
 
-        match session.state {
-            SessionState::Initiated { annex_c, annex_d } => {
-                let res = match response {
-                    DCAPIResponse::OrgIsoMDoc { data } => ac::submit_dc_response_inner(
-                        annex_c,
-                        self.trust_anchor_registry.clone(),
-                        data,
-                    )
-                    .await
-                    .map_err(|(status, e)| {
-                        JsValue::from(format!("failed with status {status:?} and error: {e:?}"))
-                    })?,
-                    DCAPIResponse::OpenId4VP { data } => {
-                        let client = OID4VPClient::new(&self.config)
-                            .map_err(|e| JsValue::from(e.to_string()))?;
-                        ad::submit_dc_response_inner(
-                            annex_d,
-                            client,
-                            self.trust_anchor_registry.clone(),
-                            data,
-                        )
-                        .await
-                        .map_err(|(status, e)| {
-                            JsValue::from(format!("failed with status {status:?} and error: {e:?}"))
-                        })?
-                    }
-                };
-
-                session.state = SessionState::Completed(res.clone());
-                self.dc_api_session
-                    .update_session(session_id, session)
-                    .await
-                    .map_err(|e| JsValue::from(e.to_string()))?;
+        let res = match self
+            .verify_response(annex_c, annex_d, response, &self.oid4vp_session_store)
+            .await
+        {
+            Ok(res) => res,
+            Err(e) => {
+                return Err(self.record_failed_submission(session_id, session, e).await);
+            }
+        };
+
+        session.state = SessionState::Completed(res.clone());
+        self.dc_api_session
+            .update_session(session_id, session)
+            .await
+            .map_err(js_error)?;
+
+        let response = serde_wasm_bindgen::to_value(&res)?;
+
+        Ok(response)
+    }
 
+    /// Returns the attempt counters of a session (`initiation_attempts`,
+    /// `failed_submission_attempts` and `locked`) without requiring the session
+    /// secret, e.g. for rate limiting at the edge.
+    #[wasm_bindgen]
+    pub async fn get_session_counters(&self, session_id: String) -> Result<JsValue, JsValue> {
+        let counters = self
+            .dc_api_session
+            .get_counters(session_id)
+            .await
+            .map_err(js_error)?
+            .ok_or_else(|| js_error(DcApiError::SessionNotFound))?;
+
+        let value = serde_wasm_bindgen::to_value(&counters)?;
+
+        Ok(value)
+    }
+
+    /// Returns the coarse status of a session (`Created`, `Initiated`, `Completed`,
+    /// `Failed`, `Expired` or `Locked`) without requiring the session secret.
+    ///
+    /// This never returns the outcome, see [DcApi::get_session_result].
+    #[wasm_bindgen]
+    pub async fn get_session_status(&self, session_id: String) -> Result<JsValue, JsValue> {
+        let status = self
+            .dc_api_session
+            .get_status(session_id)
+            .await
+            .map_err(js_error)?
+            .ok_or_else(|| js_error(DcApiError::SessionNotFound))?;
+
+        let value = serde_wasm_bindgen::to_value(&status)?;
+
+        Ok(value)
+    }
+
+    /// Returns the outcome of a completed session.
+    #[wasm_bindgen]
+    pub async fn get_session_result(
+        &self,
+        session_id: String,
+        session_secret: String,
+    ) -> Result<JsValue, JsValue> {
+        let session = self
+            .dc_api_session
+            .get_session(session_id, session_secret)
+            .await
+            .map_err(js_error)?
+            .ok_or_else(|| js_error(DcApiError::SessionNotFound))?;
+
+        match session.state {
+            SessionState::Completed(res) => {
                 let response = serde_wasm_bindgen::to_value(&res)?;
 
                 Ok(response)
             }
-            _ => Err(JsValue::from_str("Invalid Session")),
+            SessionState::Failed(reason) => Err(js_error(DcApiError::InvalidSessionState(
+                format!("session failed: {reason}"),
+            ))),
+            _ => Err(js_error(DcApiError::InvalidSessionState(
+                "session not completed".to_string(),
+            ))),
         }
     }
+
+    /// Removes a session along with its linked OID4VP session.
+    #[wasm_bindgen]
+    pub async fn remove_session(
+        &self,
+        session_id: String,
+        session_secret: String,
+    ) -> Result<(), JsValue> {
+        self.dc_api_session
+            .get_session(session_id.clone(), session_secret)
+            .await
+            .map_err(js_error)?
+            .ok_or_else(|| js_error(DcApiError::SessionNotFound))?;
+
+        self.dc_api_session
+            .remove_session_and_linked(session_id, &self.oid4vp_session_store)
+            .await
+            .map_err(js_error)
+    }
+
+    /// Removes expired sessions, and completed or failed sessions older than
+    /// `retention_seconds`, along with their linked OID4VP sessions.
+    ///
+    /// Returns the number of `expired` and `purged` sessions.
+    #[wasm_bindgen]
+    pub async fn sweep_sessions(&self, retention_seconds: u32) -> Result<JsValue, JsValue> {
+        let report = self
+            .dc_api_session
+            .sweep(
+                Duration::seconds(retention_seconds.into()),
+                &self.oid4vp_session_store,
+            )
+            .await
+            .map_err(js_error)?;
+
+        let value = serde_wasm_bindgen::to_value(&report)?;
+
+        Ok(value)
+    }
+
+    /// Initiates a request without a DC API session, see [DcApi::enable_sealed_sessions].
+    ///
+    /// The returned object contains the DC API `requests` and the sealed `token`
+    /// that must be presented back to [DcApi::submit_sealed_response].
+    #[wasm_bindgen]
+    pub async fn initiate_sealed_request(
+        &self,
+        request: JsValue,
+        user_agent: Option<String>,
+    ) -> Result<JsValue, JsValue> {
+        let sealed_sessions = self.sealed_sessions()?;
+        let request: DCAPINamespaceRequest = serde_wasm_bindgen::from_value(request)
+            .map_err(|e| js_error(DcApiError::InvalidRequest(e.to_string())))?;
+
+        // The OID4VP verifier still requires a session id, although nothing is stored.
+        let session_id = Uuid::new_v4().to_string();
+        let (requests, annex_c, annex_d) = self
+            .build_requests(&self.sealed_verifier, &request, session_id, user_agent)
+            .await?;
+        let token = sealed_sessions.seal(annex_c, annex_d).map_err(js_error)?;
+
+        let value = serde_wasm_bindgen::to_value(&SealedInitiateResponse {
+            requests: requests.requests,
+            token,
+        })?;
+
+        Ok(value)
+    }
+
+    #[wasm_bindgen]
+    pub async fn submit_sealed_response(
+        &self,
+        token: String,
+        response: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let sealed_sessions = self.sealed_sessions()?;
+        let response: DCAPIResponse = serde_wasm_bindgen::from_value(response)
+            .map_err(|e| js_error(DcApiError::InvalidResponse(e.to_string())))?;
+
+        let state = sealed_sessions.open(&token).map_err(js_error)?;
+        let res = self
+            .verify_response(
+                state.annex_c.clone(),
+                state.annex_d.clone(),
+                response,
+                &SealedOid4VpSessionStore,
+            )
+            .await
+            .map_err(js_error)?;
+        // The token is only used up by a submission that could be verified.
+        sealed_sessions.consume(&state).await.map_err(js_error)?;
+
+        let response = serde_wasm_bindgen::to_value(&res)?;
+
+        Ok(response)
+    }
+}
+
+impl DcApi {
+    /// Counts a failed submission, locking the session past the threshold.
+    ///
+    /// Returns `error`, to be surfaced to the caller.
+    async fn record_failed_submission(
+        &self,
+        session_id: String,
+        session: Session,
+        error: DcApiError,
+    ) -> JsValue {
+        js_error(
+            self.dc_api_session
+                .record_failed_submission(session_id, session, error)
+                .await,
+        )
+    }
+
+    fn sealed_sessions(&self) -> Result<&SealedSessions, JsValue> {
+        self.sealed_sessions.as_ref().ok_or_else(|| {
+            js_error(DcApiError::NotConfigured(
+                "sealed sessions are not enabled".to_string(),
+            ))
+        })
+    }
+
+    async fn build_requests(
+        &self,
+        verifier: &OID4VPVerifier,
+        request: &DCAPINamespaceRequest,
+        session_id: String,
+        user_agent: Option<String>,
+    ) -> Result<
+        (
+            DCAPIRequests,
+            ac::InitiatedSessionState,
+            ad::InitiatedSessionState,
+        ),
+        JsValue,
+    > {
+        let (annexc_res, annexc_state) = ac::initiate_inner(&self.config, request)
+            .await
+            .map_err(js_error)?;
+        let (annexd_res, annexd_state) =
+            ad::initiate_inner(&self.config, verifier, request, session_id)
+                .await
+                .map_err(js_error)?;
+
+        let mut requests = vec![DCAPIRequest::OpenId4VP {
+            data: DCAPIRequestOpenId4VP {
+                request: annexd_res.request_jwt,
+            },
+        }];
+
+        // NOTE: Chrome crashes on processing the DC API response
+        // when the request contains more than one request.
+        if let Some(user_agent) = user_agent
+            && !user_agent.as_str().contains("Chrome")
+        {
+            requests.push(DCAPIRequest::OrgIsoMDoc { data: annexc_res });
+        }
+
+        Ok((DCAPIRequests { requests }, annexc_state, annexd_state))
+    }
+
+    /// Verifies a response, keeping the status of the linked OID4VP session
+    /// in `oid4vp_session_store` in sync.
+    async fn verify_response<S: SessionStore + ?Sized>(
+        &self,
+        annex_c: ac::InitiatedSessionState,
+        annex_d: ad::InitiatedSessionState,
+        response: DCAPIResponse,
+        oid4vp_session_store: &S,
+    ) -> Result<VerificationResult, DcApiError> {
+        let ctx = VerificationContext::new(
+            &self.trust_anchor_registry,
+            &self.policy,
+            self.clock.as_ref(),
+        )
+        .with_crl_checker(self.crl_checker.as_ref())
+        .with_status_list_checker(self.status_list_checker.as_ref());
+        let res = match response {
+            DCAPIResponse::OrgIsoMDoc { data } => {
+                ac::submit_dc_response_inner(
+                    annex_c,
+                    data,
+                    annex_d.oid4vp_session_id(),
+                    oid4vp_session_store,
+                    &ctx,
+                )
+                .await?
+            }
+            DCAPIResponse::OpenId4VP { data } => {
+                let client = OID4VPClient::new(&self.config)?;
+                ad::submit_dc_response_inner(annex_d, client, data, oid4vp_session_store, &ctx)
+                    .await?
+            }
+        };
+
+        Ok(res)
+    }
+}
+
+/// Verifies the `dc-api-signature` header of a webhook request sent by [DcApi::add_webhook].
+///
+/// The signature age is checked as of `unix_timestamp` when set, e.g. to
+/// verify a captured request, and of the current time otherwise.
+#[wasm_bindgen]
+pub fn verify_webhook_signature(
+    secret: Vec<u8>,
+    header: String,
+    body: Vec<u8>,
+    tolerance_seconds: u32,
+    unix_timestamp: Option<f64>,
+) -> Result<(), JsValue> {
+    let clock: Box<dyn Clock> = match unix_timestamp {
+        Some(unix_timestamp) => {
+            let now = OffsetDateTime::from_unix_timestamp(unix_timestamp as i64)
+                .map_err(|e| js_error(DcApiError::InvalidRequest(e.to_string())))?;
+            Box::new(FixedClock(now))
+        }
+        None => Box::new(SystemClock),
+    };
+    dc_api_core::webhook::verify_signature(
+        &secret,
+        &header,
+        &body,
+        Duration::seconds(tolerance_seconds.into()),
+        clock.as_ref(),
+    )
+    .map_err(|e| js_error(DcApiError::InvalidRequest(e.to_string())))
+}
+
+/// Parses a TOML rule policy into the JSON form accepted as the `policy` of
+/// a request by [DcApi::initiate_request] and [DcApi::initiate_sealed_request].
+#[wasm_bindgen]
+pub fn parse_toml_policy(source: String) -> Result<JsValue, JsValue> {
+    let policy = RulePolicy::from_toml(&source)
+        .map_err(|e| js_error(DcApiError::InvalidRequest(format!("{e:#}"))))?;
+    let value = policy.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?;
+
+    Ok(value)
+}
+
+/// Returns the `namespaces` of a request for a preset: `mdl`, `age_over_21`,
+/// `aamva`, `real_id`, `eu_pid` or `eu_pid_age_over_18`.
+#[wasm_bindgen]
+pub fn request_preset(preset: String) -> Result<JsValue, JsValue> {
+    let preset: RequestPreset = serde_wasm_bindgen::from_value(preset.into())
+        .map_err(|e| js_error(DcApiError::InvalidRequest(e.to_string())))?;
+    let value = preset
+        .namespaces()
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())?;
+
+    Ok(value)
 }
@@ -37,6 +37,9 @@ extern "C" {
         this: &JsDcApiSessionStore,
         session_id: String,
     ) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(method, catch)]
+    async fn listSessionIds(this: &JsDcApiSessionStore) -> Result<JsValue, JsValue>;
 }
 
 #[wasm_bindgen]
@@ -65,7 +68,8 @@ impl DcApiSessionEngine for JsDcApiSessionDriver {
         Ok(())
     }
 
-    async fn get_session(&self, id: String, client_secret: String) -> Result<Option<Session>> {
+    // The client secret is checked by `SessionStorage`, which holds the pepper.
+    async fn get_session(&self, id: String, _client_secret: String) -> Result<Option<Session>> {
         let result = self
             .storage
             .getSession(id)
@@ -79,8 +83,6 @@ impl DcApiSessionEngine for JsDcApiSessionDriver {
         let session: Session = serde_wasm_bindgen::from_value(result)
             .map_err(|e| anyhow!("Failed to deserialize Session: {}", e))?;
 
-        Session::check_client_secret(&client_secret, &session.client_secret_hash)?;
-
         Ok(Some(session))
     }
 
@@ -121,4 +123,17 @@ impl DcApiSessionEngine for JsDcApiSessionDriver {
 
         Ok(())
     }
+
+    async fn list_session_ids(&self) -> Result<Vec<String>> {
+        let result = self
+            .storage
+            .listSessionIds()
+            .await
+            .map_err(|e| anyhow!("JavaScript error in listSessionIds: {:?}", e))?;
+
+        let session_ids: Vec<String> = serde_wasm_bindgen::from_value(result)
+            .map_err(|e| anyhow!("Failed to deserialize session ids: {}", e))?;
+
+        Ok(session_ids)
+    }
 }
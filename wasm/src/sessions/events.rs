@@ -0,0 +1,100 @@
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use dc_api_core::events::{EventSpawner, EventTask, SessionEvent, SessionEventHook};
+use dc_api_core::http::{Request, StatusCode};
+use dc_api_core::webhook::WebhookTransport;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "SessionEventHook")]
+    pub type JsSessionEventHook;
+
+    #[wasm_bindgen(method, catch)]
+    async fn onSessionEvent(this: &JsSessionEventHook, event: JsValue) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(typescript_type = "WebhookTransport")]
+    pub type JsWebhookTransport;
+
+    #[wasm_bindgen(method, catch)]
+    async fn send(
+        this: &JsWebhookTransport,
+        url: String,
+        headers: JsValue,
+        body: Vec<u8>,
+    ) -> Result<JsValue, JsValue>;
+}
+
+pub struct JsSessionEventHookDriver {
+    hook: JsSessionEventHook,
+}
+
+impl JsSessionEventHookDriver {
+    pub fn new(hook: JsSessionEventHook) -> Self {
+        Self { hook }
+    }
+}
+
+#[cfg_attr(target_arch="wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl SessionEventHook for JsSessionEventHookDriver {
+    async fn on_event(&self, event: &SessionEvent) -> Result<()> {
+        let event_js = event
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(|e| anyhow!("Failed to serialize session event: {}", e))?;
+
+        self.hook
+            .onSessionEvent(event_js)
+            .await
+            .map_err(|e| anyhow!("JavaScript error in onSessionEvent: {:?}", e))?;
+
+        Ok(())
+    }
+}
+
+/// Delivers session events with `spawn_local`, off the session flow.
+pub struct LocalEventSpawner;
+
+impl EventSpawner for LocalEventSpawner {
+    fn spawn(&self, task: EventTask) {
+        wasm_bindgen_futures::spawn_local(task);
+    }
+}
+
+pub struct JsWebhookTransportDriver {
+    transport: JsWebhookTransport,
+}
+
+impl JsWebhookTransportDriver {
+    pub fn new(transport: JsWebhookTransport) -> Self {
+        Self { transport }
+    }
+}
+
+#[cfg_attr(target_arch="wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl WebhookTransport for JsWebhookTransportDriver {
+    async fn send(&self, request: Request<Vec<u8>>) -> Result<StatusCode> {
+        let headers = js_sys::Object::new();
+        for (name, value) in request.headers() {
+            let value = value
+                .to_str()
+                .map_err(|e| anyhow!("Invalid header value: {}", e))?;
+            js_sys::Reflect::set(&headers, &name.as_str().into(), &value.into())
+                .map_err(|e| anyhow!("Failed to set header: {:?}", e))?;
+        }
+        let url = request.uri().to_string();
+
+        let status = self
+            .transport
+            .send(url, headers.into(), request.into_body())
+            .await
+            .map_err(|e| anyhow!("JavaScript error in send: {:?}", e))?;
+
+        let status = status
+            .as_f64()
+            .ok_or(anyhow!("WebhookTransport.send must return a status code"))?;
+        StatusCode::from_u16(status as u16).map_err(|e| anyhow!("Invalid status code: {}", e))
+    }
+}
@@ -1,5 +1,9 @@
 mod dc_api;
+mod events;
+mod nonce_cache;
 mod oid4vp;
 
 pub use dc_api::*;
+pub use events::*;
+pub use nonce_cache::*;
 pub use oid4vp::*;
@@ -0,0 +1,44 @@
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use dc_api_core::sealed::NonceCache;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "NonceCache")]
+    pub type JsNonceCache;
+
+    #[wasm_bindgen(method, catch)]
+    async fn consume(
+        this: &JsNonceCache,
+        token_id: String,
+        expires_at: f64,
+    ) -> Result<JsValue, JsValue>;
+}
+
+#[wasm_bindgen]
+pub struct JsNonceCacheDriver {
+    cache: JsNonceCache,
+}
+
+impl JsNonceCacheDriver {
+    pub fn new(cache: JsNonceCache) -> Self {
+        Self { cache }
+    }
+}
+
+#[cfg_attr(target_arch="wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl NonceCache for JsNonceCacheDriver {
+    async fn consume(&self, token_id: String, expires_at: i64) -> Result<bool> {
+        let result = self
+            .cache
+            .consume(token_id, expires_at as f64)
+            .await
+            .map_err(|e| anyhow!("JavaScript error in consume: {:?}", e))?;
+
+        result
+            .as_bool()
+            .ok_or_else(|| anyhow!("NonceCache.consume must return a boolean"))
+    }
+}
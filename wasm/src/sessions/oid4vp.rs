@@ -80,6 +80,7 @@ impl JsOid4VpSessionStore {
     ///
     /// const sessionStore = new WasmOid4VpSession(new MySessionStore());
     /// ```
+    #[allow(clippy::arc_with_non_send_sync)]
     #[wasm_bindgen(constructor)]
     pub fn new(store: Oid4VpSessionStore) -> Self {
         Self {